@@ -0,0 +1,23 @@
+use advent_of_code_2022::day20::{input_generator, mix, mix_naive};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+const INPUT: &str = include_str!("../input/2022/day20.txt");
+
+fn bench_mix(c: &mut Criterion) {
+    let input = input_generator(INPUT).unwrap();
+    let decrypted = input.iter().map(|x| x * 811589153).collect::<Vec<_>>();
+    let mut group = c.benchmark_group("day20_mix");
+    group.sample_size(10);
+    for (name, order, rounds) in [("part1", &input, 1), ("part2", &decrypted, 10)] {
+        group.bench_with_input(BenchmarkId::new("indexed_list", name), order, |b, order| {
+            b.iter(|| mix(&mut order.clone(), order, rounds))
+        });
+        group.bench_with_input(BenchmarkId::new("naive", name), order, |b, order| {
+            b.iter(|| mix_naive(&mut order.clone(), order, rounds))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_mix);
+criterion_main!(benches);
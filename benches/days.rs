@@ -0,0 +1,250 @@
+//! Benchmarks the generator and both parts of every day against the real puzzle input,
+//! and reports regressions compared to the previous run.
+//!
+//! Run with `cargo bench --bench days`, optionally followed by `-- day16 day19` to only run some days.
+//! Every run is appended to `target/bench-history.json`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use advent_of_code_2022::*;
+use criterion::black_box;
+use serde::{Deserialize, Serialize};
+
+/// How long to keep repeating a single phase. Slow phases run only once.
+const TIME_BUDGET: Duration = Duration::from_millis(500);
+const MAX_SAMPLES: usize = 100;
+/// Relative slowdown before a phase is reported as a regression.
+const REGRESSION_THRESHOLD: f64 = 0.10;
+/// Absolute slowdown (in nanoseconds) below which differences are considered noise.
+const NOISE_FLOOR: u64 = 20_000;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Run {
+    /// Seconds since the Unix epoch.
+    timestamp: u64,
+    /// The git commit that was benchmarked, if known.
+    commit: Option<String>,
+    measurements: Vec<Measurement>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Measurement {
+    day: String,
+    phase: String,
+    /// Median time of a single call, in nanoseconds.
+    median: u64,
+    samples: usize,
+}
+
+struct Bencher {
+    /// Whether we're running under `cargo bench`, rather than `cargo test`.
+    bench: bool,
+    filters: Vec<String>,
+    history: Vec<Run>,
+    measurements: Vec<Measurement>,
+    regressions: Vec<String>,
+}
+
+impl Bencher {
+    fn wants(&self, day: &str) -> bool {
+        self.filters.is_empty() || self.filters.iter().any(|filter| filter == day)
+    }
+
+    /// Times `f`, and returns the output of its last call.
+    fn measure<T>(&mut self, day: &str, phase: &str, mut f: impl FnMut() -> T) -> T {
+        let mut times = Vec::new();
+        let mut total = Duration::ZERO;
+        let output = loop {
+            let start = Instant::now();
+            let output = black_box(f());
+            let elapsed = start.elapsed();
+            times.push(elapsed.as_nanos() as u64);
+            total += elapsed;
+            if !self.bench || total >= TIME_BUDGET || times.len() >= MAX_SAMPLES {
+                break output;
+            }
+        };
+        times.sort_unstable();
+        let measurement = Measurement {
+            day: day.to_string(),
+            phase: phase.to_string(),
+            median: times[times.len() / 2],
+            samples: times.len(),
+        };
+        self.report(&measurement);
+        self.measurements.push(measurement);
+        output
+    }
+
+    fn previous(&self, day: &str, phase: &str) -> Option<&Measurement> {
+        self.history.iter().rev().find_map(|run| {
+            run.measurements
+                .iter()
+                .find(|m| m.day == day && m.phase == phase)
+        })
+    }
+
+    fn report(&mut self, measurement: &Measurement) {
+        let mut line = format!(
+            "{:<6} {:<10} {:>12} ({} samples)",
+            measurement.day,
+            measurement.phase,
+            format_nanos(measurement.median),
+            measurement.samples
+        );
+        if let Some(previous) = self.previous(&measurement.day, &measurement.phase) {
+            let change =
+                (measurement.median as f64 - previous.median as f64) / previous.median as f64;
+            line += &format!(
+                "  previous {:>12} {:>+8.1}%",
+                format_nanos(previous.median),
+                change * 100.0
+            );
+            if change > REGRESSION_THRESHOLD && measurement.median > previous.median + NOISE_FLOOR {
+                line += "  REGRESSION";
+                self.regressions.push(format!(
+                    "{} {}: {} -> {}",
+                    measurement.day,
+                    measurement.phase,
+                    format_nanos(previous.median),
+                    format_nanos(measurement.median)
+                ));
+            }
+        }
+        println!("{}", line);
+    }
+}
+
+fn format_nanos(nanos: u64) -> String {
+    match nanos {
+        0..=9_999 => format!("{} ns", nanos),
+        10_000..=9_999_999 => format!("{:.1} µs", nanos as f64 / 1e3),
+        10_000_000..=9_999_999_999 => format!("{:.1} ms", nanos as f64 / 1e6),
+        _ => format!("{:.2} s", nanos as f64 / 1e9),
+    }
+}
+
+fn history_path() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("target/bench-history.json")
+}
+
+fn load_history(path: &Path) -> Vec<Run> {
+    let Ok(json) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    serde_json::from_str(&json).unwrap_or_else(|err| {
+        eprintln!("ignoring invalid history in {}: {}", path.display(), err);
+        Vec::new()
+    })
+}
+
+fn current_commit() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn read_input(day: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("input/2022")
+        .join(format!("{}.txt", day));
+    fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("cannot read {}: {}", path.display(), err))
+        .trim_end_matches('\n')
+        .to_string()
+}
+
+macro_rules! bench_day {
+    ($bencher:expr, $day:ident, $($part:ident),+) => {
+        if $bencher.wants(stringify!($day)) {
+            let input = read_input(stringify!($day));
+            let parsed = $bencher.measure(stringify!($day), "generator", || {
+                $day::input_generator(&input).unwrap()
+            });
+            $(
+                let _ = $bencher.measure(stringify!($day), stringify!($part), || $day::$part(&parsed));
+            )+
+        }
+    };
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let path = history_path();
+    let mut bencher = Bencher {
+        bench: args.iter().any(|arg| arg == "--bench"),
+        filters: args
+            .into_iter()
+            .filter(|arg| !arg.starts_with('-'))
+            .collect(),
+        history: load_history(&path),
+        measurements: Vec::new(),
+        regressions: Vec::new(),
+    };
+
+    bench_day!(bencher, day1, part1, part2);
+    bench_day!(bencher, day2, part1, part2);
+    bench_day!(bencher, day3, part1, part2);
+    bench_day!(bencher, day4, part1, part2);
+    bench_day!(bencher, day5, part1, part2);
+    bench_day!(bencher, day6, part1, part2);
+    bench_day!(bencher, day7, part1, part2);
+    bench_day!(bencher, day8, part1, part2);
+    bench_day!(bencher, day9, part1, part2);
+    bench_day!(bencher, day10, part1, part2);
+    bench_day!(bencher, day11, part1, part2);
+    bench_day!(bencher, day12, part1, part2);
+    bench_day!(bencher, day13, part1, part2);
+    bench_day!(bencher, day14, part1, part2);
+    bench_day!(bencher, day15, part1, part2);
+    bench_day!(bencher, day16, part1, part2);
+    bench_day!(bencher, day17, part1, part2);
+    bench_day!(bencher, day18, part1, part2);
+    bench_day!(bencher, day19, part1, part2);
+    bench_day!(bencher, day20, part1, part2);
+    bench_day!(bencher, day21, part1, part2);
+    bench_day!(bencher, day22, part1, part2);
+    bench_day!(bencher, day23, part1, part2);
+    bench_day!(bencher, day24, part1, part2);
+    bench_day!(bencher, day25, part1);
+
+    if !bencher.bench {
+        // Smoke test under `cargo test --benches`, don't record anything.
+        return;
+    }
+
+    println!();
+    if bencher.regressions.is_empty() {
+        println!("No regressions.");
+    } else {
+        println!("{} regression(s):", bencher.regressions.len());
+        for regression in &bencher.regressions {
+            println!("  {}", regression);
+        }
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    bencher.history.push(Run {
+        timestamp,
+        commit: current_commit(),
+        measurements: bencher.measurements,
+    });
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(
+        &path,
+        serde_json::to_string_pretty(&bencher.history).unwrap(),
+    )
+    .unwrap();
+    println!("History written to {}", path.display());
+}
@@ -0,0 +1,160 @@
+//! Generates the registry of days used by the runner in `src/main.rs`,
+//! by scanning `src/dayN.rs` for `#[aoc_generator]` and `#[aoc]` functions.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+#[derive(Default)]
+struct DayInfo {
+    module: String,
+    /// The name of the generator function, and whether it returns a `Result`.
+    generator: Option<(String, bool)>,
+    /// The name of each part's function, and whether it returns a `Result`.
+    parts: BTreeMap<u32, (String, bool)>,
+}
+
+/// Parses the arguments of an attribute like `#[aoc(day1, part2)]`.
+fn parse_attribute<'a>(line: &'a str, name: &str) -> Option<Vec<&'a str>> {
+    let args = line
+        .trim()
+        .strip_prefix("#[")?
+        .strip_prefix(name)?
+        .strip_prefix('(')?
+        .strip_suffix(")]")?;
+    Some(args.split(',').map(|arg| arg.trim()).collect())
+}
+
+/// Finds the name and return type of the function following an attribute.
+fn parse_function(lines: &[&str]) -> (String, bool) {
+    let signature = lines
+        .iter()
+        .skip_while(|line| !line.contains("fn "))
+        .take_while(|line| !line.starts_with('}'))
+        .map(|line| line.trim())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let signature = signature.split('{').next().unwrap();
+    let name = signature
+        .split("fn ")
+        .nth(1)
+        .and_then(|rest| rest.split(['(', '<']).next())
+        .expect("attribute must be followed by a function")
+        .trim()
+        .to_string();
+    let fallible = signature
+        .split("->")
+        .nth(1)
+        .map_or(false, |output| output.trim().starts_with("Result<"));
+    (name, fallible)
+}
+
+fn scan_day(path: &Path, module: &str, days: &mut BTreeMap<u32, DayInfo>) {
+    let source = fs::read_to_string(path).unwrap();
+    let lines = source.lines().collect::<Vec<_>>();
+    for (i, line) in lines.iter().enumerate() {
+        if let Some(args) = parse_attribute(line, "aoc_generator") {
+            let day = args[0].strip_prefix("day").unwrap().parse().unwrap();
+            let info = days.entry(day).or_default();
+            info.module = module.to_string();
+            info.generator = Some(parse_function(&lines[i + 1..]));
+        } else if let Some(args) = parse_attribute(line, "aoc") {
+            // Named alternative solutions, like `#[aoc(day1, part1, fast)]`, are skipped.
+            if args.len() != 2 {
+                continue;
+            }
+            let day = args[0].strip_prefix("day").unwrap().parse().unwrap();
+            let part = args[1].strip_prefix("part").unwrap().parse().unwrap();
+            let info = days.entry(day).or_default();
+            info.module = module.to_string();
+            info.parts.insert(part, parse_function(&lines[i + 1..]));
+        }
+    }
+}
+
+fn generate(days: &BTreeMap<u32, DayInfo>) -> String {
+    let mut out = String::new();
+    writeln!(out, "pub static DAYS: &[Day] = &[").unwrap();
+    for (day, info) in days {
+        let parts = info
+            .parts
+            .keys()
+            .map(|part| part.to_string())
+            .collect::<Vec<_>>();
+        writeln!(
+            out,
+            "    Day {{ day: {}, parts: &[{}], run: {} }},",
+            day,
+            parts.join(", "),
+            info.module
+        )
+        .unwrap();
+    }
+    writeln!(out, "];").unwrap();
+
+    for info in days.values() {
+        let module = &info.module;
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "fn {}(input: &str, parts: &[u32], report: &mut DayReport) -> Result<(), Box<dyn Error>> {{",
+            module
+        )
+        .unwrap();
+        match &info.generator {
+            Some((generator, fallible)) => {
+                writeln!(out, "    let start = Instant::now();").unwrap();
+                writeln!(
+                    out,
+                    "    let input = advent_of_code_2022::{}::{}(input){};",
+                    module,
+                    generator,
+                    if *fallible { "?" } else { "" }
+                )
+                .unwrap();
+                writeln!(out, "    report.generator = Some(start.elapsed());").unwrap();
+            }
+            None => writeln!(out, "    let input = input;").unwrap(),
+        }
+        for (part, (solver, fallible)) in &info.parts {
+            writeln!(out, "    if parts.contains(&{}) {{", part).unwrap();
+            writeln!(out, "        let start = Instant::now();").unwrap();
+            writeln!(
+                out,
+                "        let answer = advent_of_code_2022::{}::{}(&input){};",
+                module,
+                solver,
+                if *fallible { "?" } else { "" }
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "        report.parts.push(PartReport {{ part: {}, answer: answer.to_string(), elapsed: start.elapsed() }});",
+                part
+            )
+            .unwrap();
+            writeln!(out, "    }}").unwrap();
+        }
+        writeln!(out, "    Ok(())").unwrap();
+        writeln!(out, "}}").unwrap();
+    }
+    out
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=src");
+    let mut days = BTreeMap::new();
+    for entry in fs::read_dir("src").unwrap() {
+        let path = entry.unwrap().path();
+        let module = path.file_stem().unwrap().to_str().unwrap().to_string();
+        let is_day = module
+            .strip_prefix("day")
+            .map_or(false, |day| day.parse::<u32>().is_ok());
+        if is_day {
+            scan_day(&path, &module, &mut days);
+        }
+    }
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("registry.rs"), generate(&days)).unwrap();
+}
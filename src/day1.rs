@@ -1,8 +1,10 @@
+use crate::util::{blocks, ParseError};
+
 #[aoc_generator(day1)]
-pub fn input_generator(input: &str) -> Vec<Vec<i32>> {
-    input
-        .split("\n\n")
-        .map(|block| block.lines().map(|line| line.parse().unwrap()).collect())
+pub fn input_generator(input: &str) -> Result<Vec<Vec<i32>>, ParseError> {
+    blocks(1, input)
+        .into_iter()
+        .map(|block| block.iter().map(|line| line.parse(line.text)).collect())
         .collect()
 }
 
@@ -50,13 +52,13 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part1(&input), 24000);
     }
 
     #[test]
     fn test_part2() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part2(&input), 45000);
     }
 }
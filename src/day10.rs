@@ -1,89 +1,77 @@
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
-#[derive(Debug, Copy, Clone)]
+use crate::util::{
+    assemble, read_letters, InstructionSet, Line, OcrError, OcrMode, ParseError, Vm,
+};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Instruction {
     AddX(i32),
     Nop,
 }
 
 impl FromStr for Instruction {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let line = Line::new(10, 1, s);
         if let Some(value) = s.strip_prefix("addx ") {
-            Ok(Instruction::AddX(value.parse().unwrap()))
+            Ok(Instruction::AddX(line.parse(value)?))
         } else if s == "noop" {
             Ok(Instruction::Nop)
         } else {
-            panic!("invalid instruction: {}", s);
+            Err(line.error(s, "invalid instruction"))
         }
     }
 }
 
-#[aoc_generator(day10)]
-pub fn input_generator(input: &str) -> Vec<Instruction> {
-    input.lines().map(|line| line.parse().unwrap()).collect()
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instruction::AddX(value) => write!(f, "addx {}", value),
+            Instruction::Nop => write!(f, "noop"),
+        }
+    }
 }
 
-#[derive(Debug)]
-struct Cpu {
-    program: Vec<Instruction>,
-    pc: usize,
-    current_instruction: Option<Instruction>,
-    remaining_cycles: usize,
-    x: i32,
-}
+/// The index of the X register.
+const X: usize = 0;
+
+impl InstructionSet for Instruction {
+    const REGISTERS: &'static [&'static str] = &["x"];
 
-impl Cpu {
-    fn new(program: Vec<Instruction>) -> Self {
-        Self {
-            program,
-            pc: 0,
-            current_instruction: None,
-            remaining_cycles: 0,
-            x: 1,
+    fn initial_registers() -> Vec<i32> {
+        vec![1]
+    }
+
+    fn cycles(&self) -> usize {
+        match self {
+            Instruction::AddX(_) => 2,
+            Instruction::Nop => 1,
         }
     }
 
-    fn step(&mut self) -> i32 {
-        if self.current_instruction.is_none() {
-            self.current_instruction = self.program.get(self.pc).cloned();
-            self.remaining_cycles = match self.current_instruction {
-                Some(Instruction::AddX(_)) => 2,
-                Some(Instruction::Nop) => 1,
-                None => {
-                    // end of file
-                    return self.x;
-                }
-            };
-        };
-        let x = self.x;
-        self.remaining_cycles -= 1;
-        if self.remaining_cycles == 0 {
-            match self.current_instruction {
-                Some(Instruction::Nop) => {
-                    // do nothing
-                }
-                Some(Instruction::AddX(value)) => {
-                    self.x += value;
-                }
-                None => {
-                    // end of file???
-                }
-            }
-            self.current_instruction = None;
-            self.pc += 1;
+    fn execute(&self, registers: &mut [i32]) {
+        match self {
+            Instruction::AddX(value) => registers[X] += value,
+            Instruction::Nop => {}
         }
-        x
     }
 }
 
+#[aoc_generator(day10)]
+pub fn input_generator(input: &str) -> Result<Vec<Instruction>, ParseError> {
+    assemble(10, input)
+}
+
 #[aoc(day10, part1)]
 pub fn part1(input: &[Instruction]) -> i32 {
-    let mut cpu = Cpu::new(input.to_vec());
+    let mut cpu = Vm::new(input.to_vec());
     let mut sum = 0;
     for cycle in 1..=220 {
-        let x = cpu.step();
+        let x = cpu.registers()[X];
+        cpu.step();
         if cycle % 40 == 20 {
             sum += cycle * x;
         }
@@ -91,13 +79,14 @@ pub fn part1(input: &[Instruction]) -> i32 {
     sum
 }
 
-#[aoc(day10, part2)]
-pub fn part2(input: &[Instruction]) -> String {
+/// Draws the CRT screen, with a `#` for every lit pixel.
+fn draw_screen(input: &[Instruction]) -> String {
     let mut screen = [[' '; 40]; 6];
-    let mut cpu = Cpu::new(input.to_vec());
+    let mut cpu = Vm::new(input.to_vec());
     for row in screen.iter_mut() {
         for (x, c) in row.iter_mut().enumerate() {
-            let value = cpu.step();
+            let value = cpu.registers()[X];
+            cpu.step();
             *c = if value >= (x as i32) - 1 && value <= (x as i32) + 1 {
                 '#'
             } else {
@@ -115,9 +104,15 @@ pub fn part2(input: &[Instruction]) -> String {
         .collect::<String>()
 }
 
+#[aoc(day10, part2)]
+pub fn part2(input: &[Instruction]) -> Result<String, OcrError> {
+    read_letters(&draw_screen(input), OcrMode::Strict)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::util::disassemble;
 
     lazy_static! {
         static ref TEST_INPUT: &'static str = include_str!("../examples/2022/day10.txt").trim();
@@ -131,36 +126,43 @@ noop
 addx 3
 addx -5"
                 .trim(),
-        );
-        let mut cpu = Cpu::new(input);
+        )
+        .unwrap();
+        let mut cpu = Vm::new(input);
         cpu.step();
-        assert_eq!(cpu.remaining_cycles, 0);
-        assert_eq!(cpu.x, 1);
+        assert_eq!(cpu.pc(), 1);
+        assert_eq!(cpu.registers()[X], 1);
         cpu.step();
-        assert_eq!(cpu.remaining_cycles, 1);
-        assert_eq!(cpu.x, 1);
+        assert_eq!(cpu.pc(), 1);
+        assert_eq!(cpu.registers()[X], 1);
         cpu.step();
-        assert_eq!(cpu.remaining_cycles, 0);
-        assert_eq!(cpu.x, 4);
+        assert_eq!(cpu.pc(), 2);
+        assert_eq!(cpu.registers()[X], 4);
         cpu.step();
-        assert_eq!(cpu.remaining_cycles, 1);
-        assert_eq!(cpu.x, 4);
+        assert_eq!(cpu.pc(), 2);
+        assert_eq!(cpu.registers()[X], 4);
         cpu.step();
-        assert_eq!(cpu.remaining_cycles, 0);
-        assert_eq!(cpu.x, -1);
+        assert!(cpu.is_halted());
+        assert_eq!(cpu.registers()[X], -1);
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let input = input_generator(&TEST_INPUT).unwrap();
+        assert_eq!(disassemble(&input).trim_end(), *TEST_INPUT);
     }
 
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part1(&input), 13140);
     }
 
     #[test]
-    fn test_part2() {
-        let input = input_generator(&TEST_INPUT);
+    fn test_draw_screen() {
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(
-            part2(&input),
+            draw_screen(&input),
             r"
 ##..##..##..##..##..##..##..##..##..##..
 ###...###...###...###...###...###...###.
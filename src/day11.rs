@@ -1,162 +1,523 @@
 use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::mem;
 
-use crate::util::lcm_64;
+use crate::util::{blocks, lcm_64, Line, ParseError};
 
 #[derive(Debug, Clone)]
 pub struct Monkey {
     items: Vec<i64>,
-    operation: Operation,
+    operation: Expression,
     test: i64,
     throw_if_true: usize,
     throw_if_false: usize,
-    total_inspected: usize,
 }
 
-#[derive(Debug, Copy, Clone)]
-pub enum Operation {
-    Add(i64),
-    Multiply(i64),
-    Square,
+/// How a monkey changes the worry level of an item it inspects.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Expression {
+    /// The worry level before the inspection.
+    Old,
+    Constant(i64),
+    Binary(Box<Expression>, Operator, Box<Expression>),
 }
 
-#[aoc_generator(day11)]
-pub fn input_generator(input: &str) -> Vec<Monkey> {
-    let mut monkeys = Vec::new();
-    for block in input.split("\n\n") {
-        let mut lines = block.lines();
-        let _number = lines
-            .next()
-            .unwrap()
-            .strip_prefix("Monkey ")
-            .unwrap()
-            .strip_suffix(':')
-            .unwrap();
-        let items = lines
-            .next()
-            .unwrap()
-            .strip_prefix("  Starting items: ")
-            .unwrap()
-            .split(", ")
-            .map(|item| item.parse::<i64>().unwrap())
-            .collect();
-        let operation = lines
-            .next()
-            .unwrap()
-            .strip_prefix("  Operation: new = ")
-            .unwrap();
-        let operation = if operation == "old * old" {
-            Operation::Square
-        } else if let Some(value) = operation.strip_prefix("old + ") {
-            Operation::Add(value.parse().unwrap())
-        } else if let Some(value) = operation.strip_prefix("old * ") {
-            Operation::Multiply(value.parse().unwrap())
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+impl Operator {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '+' => Some(Operator::Add),
+            '-' => Some(Operator::Subtract),
+            '*' => Some(Operator::Multiply),
+            '/' => Some(Operator::Divide),
+            '%' => Some(Operator::Remainder),
+            _ => None,
+        }
+    }
+
+    fn precedence(self) -> u8 {
+        match self {
+            Operator::Add | Operator::Subtract => 1,
+            Operator::Multiply | Operator::Divide | Operator::Remainder => 2,
+        }
+    }
+
+    fn symbol(self) -> char {
+        match self {
+            Operator::Add => '+',
+            Operator::Subtract => '-',
+            Operator::Multiply => '*',
+            Operator::Divide => '/',
+            Operator::Remainder => '%',
+        }
+    }
+}
+
+impl Expression {
+    /// Evaluates the expression, or returns `None` on overflow or division by zero.
+    ///
+    /// With a modulus, every intermediate result is reduced modulo it,
+    /// which is only correct if the expression `is_modular()`.
+    pub fn evaluate(&self, old: i64, modulus: Option<i64>) -> Option<i64> {
+        let value = match self {
+            Expression::Old => old,
+            &Expression::Constant(value) => value,
+            Expression::Binary(left, operator, right) => {
+                let left = left.evaluate(old, modulus)?;
+                let right = right.evaluate(old, modulus)?;
+                match operator {
+                    Operator::Add => left.checked_add(right)?,
+                    Operator::Subtract => left.checked_sub(right)?,
+                    Operator::Multiply => left.checked_mul(right)?,
+                    Operator::Divide => left.checked_div(right)?,
+                    Operator::Remainder => left.checked_rem(right)?,
+                }
+            }
+        };
+        Some(modulus.map_or(value, |modulus| value.rem_euclid(modulus)))
+    }
+
+    /// Whether the expression only adds, subtracts and multiplies,
+    /// so it can be evaluated modulo some number without changing the result modulo that number.
+    pub fn is_modular(&self) -> bool {
+        match self {
+            Expression::Old | Expression::Constant(_) => true,
+            Expression::Binary(left, operator, right) => {
+                !matches!(operator, Operator::Divide | Operator::Remainder)
+                    && left.is_modular()
+                    && right.is_modular()
+            }
+        }
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expression::Old => write!(f, "old"),
+            Expression::Constant(value) => write!(f, "{}", value),
+            Expression::Binary(left, operator, right) => {
+                // Parenthesize operands that would otherwise bind differently.
+                let needs_parens = |operand: &Expression, is_right: bool| match operand {
+                    Expression::Binary(_, inner, _) => {
+                        inner.precedence() < operator.precedence()
+                            || (is_right && inner.precedence() == operator.precedence())
+                    }
+                    _ => false,
+                };
+                for (operand, is_right) in [(left, false), (right, true)] {
+                    if is_right {
+                        write!(f, " {} ", operator.symbol())?;
+                    }
+                    if needs_parens(operand, is_right) {
+                        write!(f, "({})", operand)?;
+                    } else {
+                        write!(f, "{}", operand)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A recursive descent parser for expressions, which remembers its position in the line.
+struct ExpressionParser<'a> {
+    line: Line<'a>,
+    rest: &'a str,
+}
+
+impl<'a> ExpressionParser<'a> {
+    fn parse(line: Line<'a>, s: &'a str) -> Result<Expression, ParseError> {
+        let mut parser = Self { line, rest: s };
+        let expression = parser.expression(0)?;
+        parser.skip_whitespace();
+        match parser.rest.chars().next() {
+            None => Ok(expression),
+            Some(c) => Err(parser.error(format!("unexpected `{}`", c))),
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        self.line.error(self.rest, message)
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.rest = self.rest.trim_start();
+    }
+
+    /// Parses operands joined by operators that bind at least as tightly as `min_precedence`.
+    fn expression(&mut self, min_precedence: u8) -> Result<Expression, ParseError> {
+        let mut left = self.operand()?;
+        loop {
+            self.skip_whitespace();
+            let operator = match self.rest.chars().next().and_then(Operator::from_char) {
+                Some(operator) if operator.precedence() >= min_precedence => operator,
+                _ => return Ok(left),
+            };
+            self.rest = self.rest[1..].trim_start();
+            let operand = self.rest;
+            // All operators are left associative.
+            let right = self.expression(operator.precedence() + 1)?;
+            if matches!(operator, Operator::Divide | Operator::Remainder)
+                && right == Expression::Constant(0)
+            {
+                return Err(self.line.error(operand, "division by zero"));
+            }
+            left = Expression::Binary(Box::new(left), operator, Box::new(right));
+        }
+    }
+
+    fn operand(&mut self) -> Result<Expression, ParseError> {
+        self.skip_whitespace();
+        if let Some(rest) = self.rest.strip_prefix('(') {
+            self.rest = rest;
+            let expression = self.expression(0)?;
+            self.skip_whitespace();
+            self.rest = self
+                .rest
+                .strip_prefix(')')
+                .ok_or_else(|| self.error("expected `)`"))?;
+            Ok(expression)
+        } else if let Some(rest) = self.rest.strip_prefix("old") {
+            self.rest = rest;
+            Ok(Expression::Old)
         } else {
-            panic!("invalid operation: {}", operation)
+            let end = self
+                .rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(self.rest.len());
+            if end == 0 {
+                return Err(self.error("expected `old`, a number or `(`"));
+            }
+            let (number, rest) = self.rest.split_at(end);
+            let value = self.line.parse(number)?;
+            self.rest = rest;
+            Ok(Expression::Constant(value))
+        }
+    }
+}
+
+#[aoc_generator(day11)]
+pub fn input_generator(input: &str) -> Result<Vec<Monkey>, ParseError> {
+    let blocks = blocks(11, input);
+    blocks
+        .iter()
+        .map(|block| parse_monkey(block, blocks.len()))
+        .collect()
+}
+
+fn parse_monkey(block: &[Line], monkey_count: usize) -> Result<Monkey, ParseError> {
+    let (line, number) = field(block, 0, "Monkey ")?;
+    let _number: usize = line.parse(line.strip_suffix(number, ":")?)?;
+    let (line, items) = field(block, 1, "  Starting items: ")?;
+    let items = items
+        .split(", ")
+        .map(|item| line.parse(item))
+        .collect::<Result<_, _>>()?;
+    let (line, operation) = field(block, 2, "  Operation: new = ")?;
+    let operation = ExpressionParser::parse(line, operation)?;
+    let (line, test) = field(block, 3, "  Test: divisible by ")?;
+    let test = line.parse(test)?;
+    if test <= 0 {
+        return Err(line.error(line.text, "divisor must be positive"));
+    }
+    let (line, throw_if_true) = field(block, 4, "    If true: throw to monkey ")?;
+    let throw_if_true = parse_target(&line, throw_if_true, monkey_count)?;
+    let (line, throw_if_false) = field(block, 5, "    If false: throw to monkey ")?;
+    let throw_if_false = parse_target(&line, throw_if_false, monkey_count)?;
+    Ok(Monkey {
+        items,
+        operation,
+        test,
+        throw_if_true,
+        throw_if_false,
+    })
+}
+
+fn field<'a>(
+    block: &[Line<'a>],
+    index: usize,
+    prefix: &str,
+) -> Result<(Line<'a>, &'a str), ParseError> {
+    match block.get(index) {
+        Some(&line) => Ok((line, line.strip_prefix(line.text, prefix)?)),
+        None => {
+            let line = block.last().unwrap();
+            let end = &line.text[line.text.len()..];
+            Err(line.error(end, format!("expected `{}`", prefix.trim())))
+        }
+    }
+}
+
+fn parse_target(line: &Line, s: &str, monkey_count: usize) -> Result<usize, ParseError> {
+    let target = line.parse(s)?;
+    if target >= monkey_count {
+        return Err(line.error(s, "unknown monkey"));
+    }
+    Ok(target)
+}
+
+/// Where an item is at the start of a round, and how worried we are about it.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Item {
+    pub monkey: usize,
+    pub worry: i64,
+}
+
+/// The journey of a single item, up to the point where it starts repeating itself.
+#[derive(Debug, Clone)]
+pub struct ItemHistory {
+    /// The monkeys that inspected the item during each round, in order.
+    pub rounds: Vec<Vec<usize>>,
+    /// The round from which `rounds` repeats forever, if a cycle was found.
+    pub cycle_start: Option<usize>,
+}
+
+impl ItemHistory {
+    /// Maps a (0-based) round to the round in the history that behaves the same way.
+    fn equivalent_round(&self, round: u64) -> usize {
+        if round < self.rounds.len() as u64 {
+            return round as usize;
+        }
+        let start = self
+            .cycle_start
+            .expect("round is beyond the end of the history");
+        let length = (self.rounds.len() - start) as u64;
+        start + ((round - start as u64) % length) as usize
+    }
+
+    /// The monkeys that inspect the item during the given (0-based) round.
+    pub fn journey(&self, round: u64) -> &[usize] {
+        &self.rounds[self.equivalent_round(round)]
+    }
+
+    /// The number of times each monkey inspects the item during the first `rounds` rounds.
+    pub fn inspections(&self, rounds: u64, monkey_count: usize) -> Vec<u64> {
+        let count = |range: &[Vec<usize>], times: u64, counts: &mut Vec<u64>| {
+            for &monkey in range.iter().flatten() {
+                counts[monkey] += times;
+            }
         };
-        let test = lines
-            .next()
-            .unwrap()
-            .strip_prefix("  Test: divisible by ")
-            .unwrap();
-        let throw_if_true = lines
-            .next()
-            .unwrap()
-            .strip_prefix("    If true: throw to monkey ")
-            .unwrap();
-        let throw_if_false = lines
-            .next()
-            .unwrap()
-            .strip_prefix("    If false: throw to monkey ")
-            .unwrap();
-        monkeys.push(Monkey {
-            items,
-            operation,
-            test: test.parse().unwrap(),
-            throw_if_true: throw_if_true.parse().unwrap(),
-            throw_if_false: throw_if_false.parse().unwrap(),
-            total_inspected: 0,
-        })
+        let mut counts = vec![0; monkey_count];
+        if rounds <= self.rounds.len() as u64 {
+            count(&self.rounds[..rounds as usize], 1, &mut counts);
+            return counts;
+        }
+        let start = self
+            .cycle_start
+            .expect("rounds are beyond the end of the history");
+        let length = (self.rounds.len() - start) as u64;
+        let cycles = (rounds - start as u64) / length;
+        let remainder = ((rounds - start as u64) % length) as usize;
+        count(&self.rounds[..start], 1, &mut counts);
+        count(&self.rounds[start..], cycles, &mut counts);
+        count(&self.rounds[start..start + remainder], 1, &mut counts);
+        counts
     }
-    monkeys
 }
 
-struct Game {
+/// A monkey's operation that overflows or divides by zero for some worry level.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct InspectError {
+    pub monkey: usize,
+    pub operation: Expression,
+    pub old: i64,
+}
+
+impl Display for InspectError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "monkey {} cannot compute `{}` for old = {}",
+            self.monkey, self.operation, self.old
+        )
+    }
+}
+
+impl Error for InspectError {}
+
+pub struct Game {
     monkeys: Vec<Monkey>,
-    part2: bool,
-    test_lcm: i64,
+    /// Whether our worry is divided by 3 after each inspection.
+    relief: bool,
+    /// The LCM of the tests, if worry levels can be kept modulo it without changing the outcome.
+    modulus: Option<i64>,
 }
 
 impl Game {
-    fn new(monkeys: Vec<Monkey>, part2: bool) -> Self {
+    pub fn new(monkeys: Vec<Monkey>, relief: bool) -> Self {
         let mut test_lcm = 1;
         for monkey in monkeys.iter() {
             test_lcm = lcm_64(test_lcm, monkey.test);
         }
+        // Division (including the relief) doesn't commute with the modulo, so in that case
+        // we have to keep the actual worry levels.
+        let modular = !relief && monkeys.iter().all(|monkey| monkey.operation.is_modular());
         Self {
             monkeys,
-            part2,
-            test_lcm,
-        }
-    }
-
-    fn round(&mut self) {
-        for i in 0..self.monkeys.len() {
-            self.turn(i);
-        }
-    }
-
-    fn turn(&mut self, monkey_number: usize) {
-        let monkey: &mut Monkey = &mut self.monkeys[monkey_number];
-        // Mutate monkey first
-        let items = mem::take(&mut monkey.items);
-        monkey.total_inspected += items.len();
-        // Mutate other monkeys
-        let &Monkey {
-            operation,
-            test,
-            throw_if_true,
-            throw_if_false,
-            ..
-        } = &*monkey;
-        for mut item in items {
-            item = match operation {
-                Operation::Add(value) => (item + value) % self.test_lcm,
-                Operation::Multiply(value) => (item * value) % self.test_lcm,
-                Operation::Square => (item * item) % self.test_lcm,
+            relief,
+            modulus: modular.then_some(test_lcm),
+        }
+    }
+
+    pub fn monkeys(&self) -> &[Monkey] {
+        &self.monkeys
+    }
+
+    /// Inspects an item, and returns the monkey it's thrown to and its new worry level.
+    fn inspect(&self, monkey_number: usize, worry: i64) -> Result<(usize, i64), InspectError> {
+        let monkey = &self.monkeys[monkey_number];
+        let mut worry = monkey
+            .operation
+            .evaluate(worry, self.modulus)
+            .ok_or_else(|| InspectError {
+                monkey: monkey_number,
+                operation: monkey.operation.clone(),
+                old: worry,
+            })?;
+        if self.relief {
+            worry /= 3;
+        }
+        if worry % monkey.test == 0 {
+            Ok((monkey.throw_if_true, worry))
+        } else {
+            Ok((monkey.throw_if_false, worry))
+        }
+    }
+
+    /// Plays a single round, and returns the number of items each monkey inspected.
+    pub fn round(&mut self) -> Result<Vec<usize>, InspectError> {
+        (0..self.monkeys.len()).map(|i| self.turn(i)).collect()
+    }
+
+    fn turn(&mut self, monkey_number: usize) -> Result<usize, InspectError> {
+        let items = mem::take(&mut self.monkeys[monkey_number].items);
+        let inspected = items.len();
+        for item in items {
+            let (target, item) = self.inspect(monkey_number, item)?;
+            self.monkeys[target].items.push(item);
+        }
+        Ok(inspected)
+    }
+
+    /// The items currently held by the monkeys.
+    pub fn items(&self) -> impl Iterator<Item = Item> + '_ {
+        self.monkeys
+            .iter()
+            .enumerate()
+            .flat_map(|(monkey, m)| m.items.iter().map(move |&worry| Item { monkey, worry }))
+    }
+
+    /// Follows a single item for a round, and returns the monkeys that inspected it
+    /// along with where it is at the start of the next round.
+    ///
+    /// Items don't affect each other, so this matches what happens in `round()`.
+    pub fn item_round(&self, mut item: Item) -> Result<(Vec<usize>, Item), InspectError> {
+        let mut journey = Vec::new();
+        loop {
+            journey.push(item.monkey);
+            let (target, worry) = self.inspect(item.monkey, item.worry)?;
+            // Monkeys that haven't had their turn yet will inspect the item again this round.
+            let done = target <= item.monkey;
+            item = Item {
+                monkey: target,
+                worry,
             };
-            if !self.part2 {
-                item /= 3;
+            if done {
+                return Ok((journey, item));
+            }
+        }
+    }
+
+    /// Follows a single item until it returns to a state it has been in before,
+    /// or for at most `max_rounds` rounds.
+    ///
+    /// When the worry levels are kept modulo the tests' LCM there is always a cycle eventually,
+    /// otherwise there might not be one.
+    pub fn item_history(
+        &self,
+        mut item: Item,
+        max_rounds: u64,
+    ) -> Result<ItemHistory, InspectError> {
+        let mut seen = HashMap::new();
+        let mut rounds = Vec::new();
+        let mut cycle_start = None;
+        while (rounds.len() as u64) < max_rounds {
+            if let Some(&start) = seen.get(&item) {
+                cycle_start = Some(start);
+                break;
+            }
+            seen.insert(item, rounds.len());
+            let (journey, next) = self.item_round(item)?;
+            rounds.push(journey);
+            item = next;
+        }
+        Ok(ItemHistory {
+            rounds,
+            cycle_start,
+        })
+    }
+
+    /// The histories of all items currently held by the monkeys.
+    pub fn item_histories(&self, max_rounds: u64) -> Result<Vec<ItemHistory>, InspectError> {
+        self.items()
+            .map(|item| self.item_history(item, max_rounds))
+            .collect()
+    }
+
+    /// The number of items each monkey inspects during the first `rounds` rounds,
+    /// without simulating every round.
+    pub fn inspections(&self, rounds: u64) -> Result<Vec<u64>, InspectError> {
+        let mut counts = vec![0; self.monkeys.len()];
+        for history in self.item_histories(rounds)? {
+            let item_counts = history.inspections(rounds, self.monkeys.len());
+            for (count, item_count) in counts.iter_mut().zip(item_counts) {
+                *count += item_count;
             }
-            if item % test == 0 {
-                self.monkeys[throw_if_true].items.push(item);
-            } else {
-                self.monkeys[throw_if_false].items.push(item);
+        }
+        Ok(counts)
+    }
+
+    /// The number of items each monkey inspects during each of the first `rounds` rounds.
+    pub fn histogram(&self, rounds: usize) -> Result<Vec<Vec<u64>>, InspectError> {
+        let mut histogram = vec![vec![0; self.monkeys.len()]; rounds];
+        for history in self.item_histories(rounds as u64)? {
+            for (round, counts) in histogram.iter_mut().enumerate() {
+                for &monkey in history.journey(round as u64) {
+                    counts[monkey] += 1;
+                }
             }
         }
+        Ok(histogram)
+    }
+
+    /// The product of the number of inspections of the two most active monkeys.
+    pub fn monkey_business(&self, rounds: u64) -> Result<u128, InspectError> {
+        let mut counts = self.inspections(rounds)?;
+        counts.sort_unstable_by_key(|&count| Reverse(count));
+        Ok(counts.iter().take(2).map(|&count| count as u128).product())
     }
 }
 
 #[aoc(day11, part1)]
-pub fn part1(input: &[Monkey]) -> usize {
-    let mut game = Game::new(input.to_vec(), false);
-    for _ in 0..20 {
-        game.round();
-    }
-    game.monkeys
-        .sort_unstable_by_key(|monkey| Reverse(monkey.total_inspected));
-    game.monkeys[0].total_inspected * game.monkeys[1].total_inspected
+pub fn part1(input: &[Monkey]) -> Result<u128, InspectError> {
+    Game::new(input.to_vec(), true).monkey_business(20)
 }
 
 #[aoc(day11, part2)]
-pub fn part2(input: &[Monkey]) -> usize {
-    let mut game = Game::new(input.to_vec(), true);
-    for _ in 0..10000 {
-        game.round();
-    }
-    game.monkeys
-        .sort_unstable_by_key(|monkey| Reverse(monkey.total_inspected));
-    game.monkeys[0].total_inspected * game.monkeys[1].total_inspected
+pub fn part2(input: &[Monkey]) -> Result<u128, InspectError> {
+    Game::new(input.to_vec(), false).monkey_business(10000)
 }
 
 #[cfg(test)]
@@ -169,15 +530,15 @@ mod tests {
 
     #[test]
     fn test_part1_example() {
-        let monkeys = input_generator(&TEST_INPUT);
-        let mut game = Game::new(monkeys, false);
-        game.round();
+        let monkeys = input_generator(&TEST_INPUT).unwrap();
+        let mut game = Game::new(monkeys, true);
+        game.round().unwrap();
         assert_eq!(game.monkeys[0].items, vec![20, 23, 27, 26]);
         assert_eq!(game.monkeys[1].items, vec![2080, 25, 167, 207, 401, 1046]);
         assert_eq!(game.monkeys[2].items, vec![]);
         assert_eq!(game.monkeys[3].items, vec![]);
         for _ in 1..20 {
-            game.round();
+            game.round().unwrap();
         }
         assert_eq!(game.monkeys[0].items, vec![10, 12, 14, 26, 34]);
         assert_eq!(game.monkeys[1].items, vec![245, 93, 53, 199, 115]);
@@ -185,15 +546,123 @@ mod tests {
         assert_eq!(game.monkeys[3].items, vec![]);
     }
 
+    #[test]
+    fn test_item_round() {
+        let game = Game::new(input_generator(&TEST_INPUT).unwrap(), true);
+        let item = game.items().next().unwrap();
+        assert_eq!(
+            item,
+            Item {
+                monkey: 0,
+                worry: 79
+            }
+        );
+        let (journey, item) = game.item_round(item).unwrap();
+        assert_eq!(journey, vec![0, 3]);
+        assert_eq!(
+            item,
+            Item {
+                monkey: 1,
+                worry: 167
+            }
+        );
+    }
+
+    #[test]
+    fn test_inspections() {
+        let game = Game::new(input_generator(&TEST_INPUT).unwrap(), false);
+        assert_eq!(game.inspections(1000), Ok(vec![5204, 4792, 199, 5192]));
+        assert_eq!(game.inspections(10000), Ok(vec![52166, 47830, 1938, 52013]));
+        // Every item cycles well before 10000 rounds, so the counts above come from the cycles.
+        let histories = game.item_histories(u64::MAX).unwrap();
+        assert!(histories
+            .iter()
+            .all(|history| history.cycle_start.is_some() && history.rounds.len() < 10000));
+        assert_eq!(
+            game.inspections(1_000_000_000_000),
+            Ok(vec![
+                5217653508757,
+                4782346491239,
+                193256578955,
+                5202028508760
+            ])
+        );
+    }
+
+    #[test]
+    fn test_histogram() {
+        let mut game = Game::new(input_generator(&TEST_INPUT).unwrap(), false);
+        let histogram = game.histogram(100).unwrap();
+        for counts in histogram {
+            let expected = game.round().unwrap().into_iter().map(|count| count as u64);
+            assert_eq!(counts, expected.collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_invalid_operation() {
+        let input = TEST_INPUT.replace("new = old * 19", "new = old ^ 19");
+        let err = input_generator(&input).unwrap_err();
+        assert_eq!((err.day, err.line, err.column), (11, 3, 24));
+        assert_eq!(err.message, "unexpected `^`");
+        assert_eq!(err.text, "  Operation: new = old ^ 19");
+    }
+
+    fn parse_expression(s: &str) -> Result<Expression, ParseError> {
+        ExpressionParser::parse(Line::new(11, 1, s), s)
+    }
+
+    #[test]
+    fn test_expression() {
+        let expression = parse_expression("old * (old - 3) % 7 + 2 * old / 4").unwrap();
+        assert_eq!(expression.to_string(), "old * (old - 3) % 7 + 2 * old / 4");
+        assert_eq!(expression.evaluate(10, None), Some(5));
+        assert!(!expression.is_modular());
+        let expression = parse_expression("old - (old - 1)").unwrap();
+        assert_eq!(expression.to_string(), "old - (old - 1)");
+        assert_eq!(expression.evaluate(5, None), Some(1));
+        assert!(expression.is_modular());
+        let expression = parse_expression("old / (old - 5)").unwrap();
+        assert_eq!(expression.evaluate(5, None), None);
+        let err = parse_expression("old % 0 + 1").unwrap_err();
+        assert_eq!((err.column, err.message.as_str()), (7, "division by zero"));
+        assert_eq!(
+            parse_expression("(old * old").unwrap_err().message,
+            "expected `)`"
+        );
+        assert_eq!(parse_expression("old + ").unwrap_err().column, 7);
+    }
+
+    #[test]
+    fn test_non_modular_operation() {
+        let input = TEST_INPUT.replace("new = old * 19", "new = (old + 1) * 19 / 2");
+        let mut game = Game::new(input_generator(&input).unwrap(), true);
+        assert_eq!(game.modulus, None);
+        let histogram = game.histogram(20).unwrap();
+        for counts in histogram {
+            let expected = game.round().unwrap().into_iter().map(|count| count as u64);
+            assert_eq!(counts, expected.collect::<Vec<_>>());
+        }
+        // Without relief or a modulus, the worry levels soon overflow.
+        let err = part2(&input_generator(&input).unwrap()).unwrap_err();
+        assert_eq!(err.operation.to_string(), "old * old");
+        let input = TEST_INPUT.replace("new = old * 19", "new = old / (old - 79)");
+        let err = part1(&input_generator(&input).unwrap()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "monkey 0 cannot compute `old / (old - 79)` for old = 79"
+        );
+    }
+
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
-        assert_eq!(part1(&input), 10605);
+        let input = input_generator(&TEST_INPUT).unwrap();
+        assert_eq!(part1(&input), Ok(10605));
     }
 
     #[test]
     fn test_part2() {
-        let input = input_generator(&TEST_INPUT);
-        assert_eq!(part2(&input), 2713310158);
+        let input = input_generator(&TEST_INPUT).unwrap();
+        assert_eq!(part2(&input), Ok(2713310158));
     }
 }
@@ -1,51 +1,57 @@
-use std::collections::HashMap;
-
 use pathfinding::prelude::dijkstra;
 
-use crate::util::Vector2D;
+use crate::util::{Grid, Line, ParseError, Vector2D};
 
 #[derive(Debug, Clone)]
 pub struct HeightMap {
-    squares: HashMap<Vector2D, i8>,
+    squares: Grid<i8>,
     start: Vector2D,
     goal: Vector2D,
 }
 
 #[aoc_generator(day12)]
-pub fn input_generator(input: &str) -> HeightMap {
-    let mut squares = HashMap::new();
-    let mut start = Vector2D::zero();
-    let mut goal = Vector2D::zero();
-    for (y, row) in input.lines().enumerate() {
-        for (x, c) in row.char_indices() {
-            let pos = Vector2D::new(x as i32, y as i32);
-            let elevation = match c {
-                'a'..='z' => c,
-                'S' => {
-                    start = pos;
-                    'a'
-                }
-                'E' => {
-                    goal = pos;
-                    'z'
-                }
-                _ => panic!("invalid elevation: {}", c),
-            };
-            squares.insert(pos, ((elevation as u8) - b'a') as i8);
+pub fn input_generator(input: &str) -> Result<HeightMap, ParseError> {
+    let map = Grid::parse(12, input, |c| {
+        matches!(c, 'a'..='z' | 'S' | 'E').then_some(c)
+    })?;
+    let find_marker = |marker: char, name: &str| {
+        let mut found = map.iter().filter(|&(_, &c)| c == marker);
+        match (found.next(), found.next()) {
+            (Some((pos, _)), None) => Ok(pos),
+            (Some(_), Some((pos, _))) => {
+                let text = input.lines().nth(pos.y() as usize).unwrap();
+                let line = Line::new(12, pos.y() as usize + 1, text);
+                Err(line.error(&text[pos.x() as usize..], "duplicate marker"))
+            }
+            (None, _) => {
+                let text = input.lines().last().unwrap_or_default();
+                let line = Line::new(12, input.lines().count(), text);
+                Err(line.error(&text[text.len()..], format!("missing {} marker", name)))
+            }
         }
-    }
-    HeightMap {
+    };
+    let start = find_marker('S', "start")?;
+    let goal = find_marker('E', "goal")?;
+    let squares = map.map(|&c| {
+        let elevation = match c {
+            'S' => 'a',
+            'E' => 'z',
+            c => c,
+        };
+        ((elevation as u8) - b'a') as i8
+    });
+    Ok(HeightMap {
         squares,
         start,
         goal,
-    }
+    })
 }
 
 fn get_neighbours(map: &HeightMap, pos: Vector2D) -> Vec<(Vector2D, i32)> {
-    pos.neighbours()
-        .filter_map(|neighbour| {
-            let current = map.squares.get(&pos).copied().unwrap();
-            let next = map.squares.get(&neighbour).copied()?;
+    let current = map.squares[pos];
+    map.squares
+        .neighbours(pos)
+        .filter_map(|(neighbour, &next)| {
             if next - current <= 1 {
                 Some((neighbour, 1))
             } else {
@@ -76,7 +82,7 @@ pub fn part2(input: &HeightMap) -> i32 {
                 input
                     .squares
                     .iter()
-                    .filter_map(|(&pos, &elevation)| {
+                    .filter_map(|(pos, &elevation)| {
                         if elevation == 0 {
                             // Moving from the start marker to a real starting point is free.
                             Some((pos, 0))
@@ -111,13 +117,13 @@ abdefghi"
 
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part1(&input), 31);
     }
 
     #[test]
     fn test_part2() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part2(&input), 29);
     }
 }
@@ -2,6 +2,8 @@ use std::cmp::Ordering;
 
 use itertools::{EitherOrBoth, Itertools};
 
+use crate::util::{blocks, Line, ParseError};
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Packet {
     Number(i32),
@@ -10,51 +12,54 @@ pub enum Packet {
 
 pub type Input = Vec<(Packet, Packet)>;
 
-fn parse_packet(s: &str) -> Packet {
-    let (packet, rest) = consume_packet(s);
-    assert!(rest.is_empty());
-    packet
+fn parse_packet(line: &Line) -> Result<Packet, ParseError> {
+    let (packet, rest) = consume_packet(line, line.text)?;
+    if !rest.is_empty() {
+        return Err(line.error(rest, "unexpected text after packet"));
+    }
+    Ok(packet)
 }
 
-fn consume_packet(s: &str) -> (Packet, &str) {
+fn consume_packet<'a>(line: &Line<'a>, s: &'a str) -> Result<(Packet, &'a str), ParseError> {
     if let Some(mut s) = s.strip_prefix('[') {
         let mut packets = Vec::new();
         loop {
             if let Some(s) = s.strip_prefix(']') {
-                return (Packet::List(packets), s);
+                return Ok((Packet::List(packets), s));
+            } else if s.is_empty() {
+                return Err(line.error(s, "expected `]`"));
             } else {
-                let (packet, rest) = consume_packet(s);
+                let (packet, rest) = consume_packet(line, s)?;
                 packets.push(packet);
                 s = rest.strip_prefix(',').unwrap_or(rest);
             }
         }
-    } else if let Some(offset) = s.find(|c: char| !c.is_ascii_digit()) {
-        let (digits, s) = s.split_at(offset);
-        (Packet::Number(digits.parse().expect("invalid number")), s)
     } else {
-        (Packet::Number(s.parse().expect("invalid number")), "")
+        let offset = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (digits, s) = s.split_at(offset);
+        Ok((Packet::Number(line.parse(digits)?), s))
     }
 }
 
 #[aoc_generator(day13)]
-pub fn input_generator(input: &str) -> Input {
-    input
-        .split("\n\n")
-        .map(|block| {
-            let mut lines = block.lines();
-            let left = parse_packet(lines.next().unwrap());
-            let right = parse_packet(lines.next().unwrap());
-            (left, right)
+pub fn input_generator(input: &str) -> Result<Input, ParseError> {
+    blocks(13, input)
+        .iter()
+        .map(|block| match block[..] {
+            [left, right] => Ok((parse_packet(&left)?, parse_packet(&right)?)),
+            [line] => Err(line.error(&line.text[line.text.len()..], "expected a pair of packets")),
+            [_, _, extra, ..] => Err(extra.error(extra.text, "expected a blank line")),
+            [] => unreachable!(),
         })
         .collect()
 }
 
-impl PartialOrd for Packet {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+impl Ord for Packet {
+    fn cmp(&self, other: &Self) -> Ordering {
         match (self, other) {
             (Packet::Number(left), Packet::Number(right)) => {
                 // If both values are integers, the lower integer should come first.
-                left.partial_cmp(right)
+                left.cmp(right)
             }
             (Packet::List(left), Packet::List(right)) => {
                 // If both values are lists, compare the first value of each list,
@@ -63,41 +68,41 @@ impl PartialOrd for Packet {
                     match pair {
                         EitherOrBoth::Both(left, right) => {
                             match left.cmp(right) {
-                                ord @ (Ordering::Less | Ordering::Greater) => return Some(ord),
+                                ord @ (Ordering::Less | Ordering::Greater) => return ord,
                                 Ordering::Equal => {}
                             };
                         }
                         EitherOrBoth::Right(_) => {
                             // If the left list runs out of items first,
                             // the inputs are in the right order.
-                            return Some(Ordering::Less);
+                            return Ordering::Less;
                         }
                         EitherOrBoth::Left(_) => {
                             // If the right list runs out of items first,
                             // the inputs are not in the right order.
-                            return Some(Ordering::Greater);
+                            return Ordering::Greater;
                         }
                     }
                 }
                 // If the lists are the same length and no comparison makes a decision
                 // about the order, continue checking the next part of the input.
-                Some(Ordering::Equal)
+                Ordering::Equal
             }
             (left @ Packet::Number(_), right @ Packet::List(_)) => {
                 // If exactly one value is an integer, convert the integer to a list
                 // which contains that integer as its only value, then retry the comparison.
-                Packet::List(vec![left.clone()]).partial_cmp(right)
+                Packet::List(vec![left.clone()]).cmp(right)
             }
             (left @ Packet::List(_), right @ Packet::Number(_)) => {
-                left.partial_cmp(&Packet::List(vec![right.clone()]))
+                left.cmp(&Packet::List(vec![right.clone()]))
             }
         }
     }
 }
 
-impl Ord for Packet {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
+impl PartialOrd for Packet {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
@@ -171,13 +176,13 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part1(&input), 13);
     }
 
     #[test]
     fn test_part2() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part2(&input), 140);
     }
 }
@@ -1,45 +1,48 @@
-use std::collections::HashSet;
-
-use crate::util::{array_windows, Vector2D};
+use crate::util::{array_windows, lines, BoundingBox, Grid, ParseError, Vector2D};
 
 pub type Input = Vec<Vec<Vector2D>>;
 
 #[aoc_generator(day14)]
-pub fn input_generator(input: &str) -> Input {
-    input
-        .lines()
+pub fn input_generator(input: &str) -> Result<Input, ParseError> {
+    lines(14, input)
         .map(|line| {
-            line.split(" -> ")
+            line.text
+                .split(" -> ")
                 .map(|coords| {
-                    let (x, y) = coords.split_once(',').unwrap();
-                    Vector2D::new(x.parse().unwrap(), y.parse().unwrap())
+                    let (x, y) = line.split_once(coords, ",")?;
+                    Ok(Vector2D::new(line.parse(x)?, line.parse(y)?))
                 })
                 .collect()
         })
         .collect()
 }
 
-type Cave = HashSet<Vector2D>;
+type Cave = Grid<bool>;
 
 fn make_line(start: Vector2D, end: Vector2D) -> impl Iterator<Item = Vector2D> {
-    let min_x = start.x().min(end.x());
-    let max_x = start.x().max(end.x());
-    let min_y = start.y().min(end.y());
-    let max_y = start.y().max(end.y());
-    (min_x..=max_x).flat_map(move |x| (min_y..=max_y).map(move |y| Vector2D::new(x, y)))
+    BoundingBox::from_points([start, end]).unwrap().into_iter()
 }
 
 fn place_rocks(rocks: &Input) -> Cave {
-    rocks
+    let blocks = rocks
         .iter()
         .flat_map(|rock| {
             array_windows::<_, 2>(rock).flat_map(|&[start, end]| make_line(start, end))
         })
-        .collect()
+        .collect::<Vec<_>>();
+    let bounds = BoundingBox::from_points(blocks.iter().copied()).unwrap();
+    let mut cave = Cave::with_bounds(bounds.min, bounds.max, false);
+    for block in blocks {
+        cave[block] = true;
+    }
+    cave
 }
 
-fn drop_sand(cave: &Cave, source: Vector2D, floor: Option<i32>) -> Option<Vector2D> {
-    let max_y = cave.iter().map(|pos| pos.y()).max().unwrap();
+fn is_blocked(cave: &Cave, pos: Vector2D) -> bool {
+    cave.get(pos).copied().unwrap_or(false)
+}
+
+fn drop_sand(cave: &Cave, source: Vector2D, max_y: i32, floor: Option<i32>) -> Option<Vector2D> {
     let mut pos = source;
     loop {
         if let Some(floor) = floor {
@@ -54,19 +57,19 @@ fn drop_sand(cave: &Cave, source: Vector2D, floor: Option<i32>) -> Option<Vector
             }
         }
         let down = pos + Vector2D::new(0, 1);
-        if !cave.contains(&down) {
+        if !is_blocked(cave, down) {
             // Move down
             pos = down;
             continue;
         }
         let down_left = pos + Vector2D::new(-1, 1);
-        if !cave.contains(&down_left) {
+        if !is_blocked(cave, down_left) {
             // Move down-left
             pos = down_left;
             continue;
         }
         let down_right = pos + Vector2D::new(1, 1);
-        if !cave.contains(&down_right) {
+        if !is_blocked(cave, down_right) {
             // Move down-right
             pos = down_right;
             continue;
@@ -76,13 +79,19 @@ fn drop_sand(cave: &Cave, source: Vector2D, floor: Option<i32>) -> Option<Vector
     }
 }
 
+fn max_rock_y(rocks: &Input) -> i32 {
+    rocks.iter().flatten().map(|pos| pos.y()).max().unwrap()
+}
+
 #[aoc(day14, part1)]
 pub fn part1(input: &Input) -> i32 {
     let mut cave = place_rocks(input);
+    let max_y = max_rock_y(input);
     let source = Vector2D::new(500, 0);
     let mut sand_count = 0;
-    while let Some(sand_pos) = drop_sand(&cave, source, None) {
-        cave.insert(sand_pos);
+    while let Some(sand_pos) = drop_sand(&cave, source, max_y, None) {
+        cave.grow_to_include(sand_pos, false);
+        cave[sand_pos] = true;
         sand_count += 1;
     }
     sand_count
@@ -91,11 +100,13 @@ pub fn part1(input: &Input) -> i32 {
 #[aoc(day14, part2)]
 pub fn part2(input: &Input) -> i32 {
     let mut cave = place_rocks(input);
-    let floor = cave.iter().map(|pos| pos.y()).max().unwrap() + 2;
+    let max_y = max_rock_y(input);
+    let floor = max_y + 2;
     let source = Vector2D::new(500, 0);
     let mut sand_count = 0;
-    while let Some(sand_pos) = drop_sand(&cave, source, Some(floor)) {
-        cave.insert(sand_pos);
+    while let Some(sand_pos) = drop_sand(&cave, source, max_y, Some(floor)) {
+        cave.grow_to_include(sand_pos, false);
+        cave[sand_pos] = true;
         sand_count += 1;
         if sand_pos == source {
             break;
@@ -117,13 +128,13 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part1(&input), 24);
     }
 
     #[test]
     fn test_part2() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part2(&input), 93);
     }
 }
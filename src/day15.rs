@@ -1,4 +1,7 @@
-use crate::util::Vector2D;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::util::{lines, Line, ParseError, Vector2D};
 
 #[derive(Debug, Clone)]
 pub struct SensorReading {
@@ -6,23 +9,22 @@ pub struct SensorReading {
     beacon: Vector2D,
 }
 
-fn parse_pos(s: &str) -> Vector2D {
-    let s = s.strip_prefix("x=").unwrap();
-    let (x, y) = s.split_once(", y=").unwrap();
-    Vector2D::new(x.parse().unwrap(), y.parse().unwrap())
+fn parse_pos(line: &Line, s: &str) -> Result<Vector2D, ParseError> {
+    let s = line.strip_prefix(s, "x=")?;
+    let (x, y) = line.split_once(s, ", y=")?;
+    Ok(Vector2D::new(line.parse(x)?, line.parse(y)?))
 }
 
 #[aoc_generator(day15)]
-pub fn input_generator(input: &str) -> Vec<SensorReading> {
-    input
-        .lines()
+pub fn input_generator(input: &str) -> Result<Vec<SensorReading>, ParseError> {
+    lines(15, input)
         .map(|line| {
-            let s = line.strip_prefix("Sensor at ").unwrap();
-            let (sensor, beacon) = s.split_once(": closest beacon is at ").unwrap();
-            SensorReading {
-                sensor: parse_pos(sensor),
-                beacon: parse_pos(beacon),
-            }
+            let s = line.strip_prefix(line.text, "Sensor at ")?;
+            let (sensor, beacon) = line.split_once(s, ": closest beacon is at ")?;
+            Ok(SensorReading {
+                sensor: parse_pos(&line, sensor)?,
+                beacon: parse_pos(&line, beacon)?,
+            })
         })
         .collect()
 }
@@ -49,8 +51,11 @@ fn count_positions_without_beacons(readings: &[SensorReading], y: i32) -> usize
         .map(|reading| reading.sensor.x() + (reading.sensor - reading.beacon).manhattan_distance())
         .max()
         .unwrap();
-    (min_x..=max_x)
-        .filter(|&x| is_position_without_beacon(readings, Vector2D::new(x, y)))
+    #[cfg(not(feature = "parallel"))]
+    let xs = min_x..=max_x;
+    #[cfg(feature = "parallel")]
+    let xs = (min_x..=max_x).into_par_iter();
+    xs.filter(|&x| is_position_without_beacon(readings, Vector2D::new(x, y)))
         .count()
 }
 
@@ -59,35 +64,43 @@ pub fn part1(input: &[SensorReading]) -> usize {
     count_positions_without_beacons(input, 2_000_000)
 }
 
-fn find_distress_beacon(readings: &[SensorReading], max_coord: i32) -> Vector2D {
-    for y in 0..=max_coord {
-        let mut x = 0;
-        while x <= max_coord {
-            let pos = Vector2D::new(x, y);
-            if let Some(reading) = readings
-                .iter()
-                .find(|reading| is_within_reading_range(reading, pos))
-            {
-                // Skip ahead along this row until we're out of range of this reading.
-                let to_beacon = reading.beacon - reading.sensor;
-                let to_pos = pos - reading.sensor;
-                x = reading.sensor.x() + to_beacon.manhattan_distance() - to_pos.y().abs() + 1;
-                debug_assert!(is_within_reading_range(reading, Vector2D::new(x - 1, y)));
-                debug_assert!(!is_within_reading_range(reading, Vector2D::new(x, y)));
-            } else {
-                // No sensor reading in range, this must be the distress beacon!
-                return pos;
-            }
+fn find_gap_in_row(readings: &[SensorReading], y: i32, max_coord: i32) -> Option<Vector2D> {
+    let mut x = 0;
+    while x <= max_coord {
+        let pos = Vector2D::new(x, y);
+        if let Some(reading) = readings
+            .iter()
+            .find(|reading| is_within_reading_range(reading, pos))
+        {
+            // Skip ahead along this row until we're out of range of this reading.
+            let to_beacon = reading.beacon - reading.sensor;
+            let to_pos = pos - reading.sensor;
+            x = reading.sensor.x() + to_beacon.manhattan_distance() - to_pos.y().abs() + 1;
+            debug_assert!(is_within_reading_range(reading, Vector2D::new(x - 1, y)));
+            debug_assert!(!is_within_reading_range(reading, Vector2D::new(x, y)));
+        } else {
+            // No sensor reading in range, this must be the distress beacon!
+            return Some(pos);
         }
     }
-    panic!("distress beacon not found")
+    None
+}
+
+fn find_distress_beacon(readings: &[SensorReading], max_coord: i32) -> Vector2D {
+    #[cfg(not(feature = "parallel"))]
+    let beacon = (0..=max_coord).find_map(|y| find_gap_in_row(readings, y, max_coord));
+    #[cfg(feature = "parallel")]
+    let beacon = (0..=max_coord)
+        .into_par_iter()
+        .find_map_any(|y| find_gap_in_row(readings, y, max_coord));
+    beacon.expect("distress beacon not found")
 }
 
 #[aoc(day15, part2)]
 pub fn part2(input: &[SensorReading]) -> i64 {
     let max_coord = 4_000_000;
-    let pos = find_distress_beacon(input, max_coord);
-    (pos.x() as i64) * (max_coord as i64) + (pos.y() as i64)
+    let pos = Vector2D::<i64>::from(find_distress_beacon(input, max_coord));
+    pos.x() * i64::from(max_coord) + pos.y()
 }
 
 #[cfg(test)]
@@ -100,13 +113,13 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(count_positions_without_beacons(&input, 10), 26);
     }
 
     #[test]
     fn test_part2() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(find_distress_beacon(&input, 20), Vector2D::new(14, 11));
     }
 }
@@ -1,6 +1,11 @@
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 
-use pathfinding::directed::dijkstra::dijkstra_all;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::util::{lines, ParseError};
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct Valve {
@@ -10,187 +15,385 @@ pub struct Valve {
 }
 
 #[aoc_generator(day16)]
-pub fn input_generator(input: &str) -> Vec<Valve> {
-    input
-        .lines()
+pub fn input_generator(input: &str) -> Result<Vec<Valve>, ParseError> {
+    let lines = lines(16, input).collect::<Vec<_>>();
+    let names = lines
+        .iter()
+        .filter_map(|line| Some(line.text.strip_prefix("Valve ")?.split_once(' ')?.0))
+        .collect::<HashSet<_>>();
+    if !names.contains("AA") {
+        return Err(ParseError::new(16, 1, 1, "", "missing start valve `AA`"));
+    }
+    let valves = lines
+        .iter()
         .map(|line| {
-            let s = line.strip_prefix("Valve ").unwrap();
-            let (name, s) = s.split_once(" has flow rate=").unwrap();
-            let (flow_rate, s) = s.split_once("; tunnel").unwrap();
+            let s = line.strip_prefix(line.text, "Valve ")?;
+            let (name, s) = line.split_once(s, " has flow rate=")?;
+            let (flow_rate, s) = line.split_once(s, "; tunnel")?;
             let tunnels = s
                 .strip_prefix("s lead to valves ")
                 .or_else(|| s.strip_prefix(" leads to valve "))
-                .unwrap();
-            Valve {
+                .ok_or_else(|| line.error(s, "expected `s lead to valves` or `leads to valve`"))?;
+            let tunnels = tunnels
+                .split(", ")
+                .map(|tunnel| {
+                    if names.contains(tunnel) {
+                        Ok(tunnel.to_string())
+                    } else {
+                        Err(line.error(tunnel, "unknown valve"))
+                    }
+                })
+                .collect::<Result<_, _>>()?;
+            Ok(Valve {
                 name: name.to_string(),
-                flow_rate: flow_rate.parse().unwrap(),
-                tunnels: tunnels.split(", ").map(|x| x.to_string()).collect(),
-            }
+                flow_rate: line.parse(flow_rate)?,
+                tunnels,
+            })
         })
-        .collect()
+        .collect::<Result<Vec<_>, _>>()?;
+    // Open valves are tracked in a `u64`.
+    if let Some((i, _)) = valves
+        .iter()
+        .enumerate()
+        .filter(|(_, valve)| valve.flow_rate > 0)
+        .nth(64)
+    {
+        let line = lines[i];
+        return Err(line.error(line.text, "at most 64 valves can have a positive flow rate"));
+    }
+    Ok(valves)
 }
 
-type DistanceMap = HashMap<(String, String), u32>;
+/// The valves, interned to small indices, along with the time it takes to walk
+/// from any valve to a working valve.
+#[derive(Debug, Clone)]
+pub struct Network {
+    /// The names of all valves, with the working valves first.
+    names: Vec<String>,
+    /// The flow rate of each working valve. Valve `i` is open if bit `i` of a mask is set.
+    flow_rates: Vec<u32>,
+    /// The shortest distance from each valve to each working valve.
+    distances: Vec<Vec<u32>>,
+}
 
-fn make_distance_map(valves: &HashMap<String, Valve>) -> DistanceMap {
-    let mut map = DistanceMap::new();
-    for start in valves.values() {
-        map.insert((start.name.clone(), start.name.clone()), 0);
-        for (dest, (_, distance)) in dijkstra_all(start, |valve| {
-            valve
-                .tunnels
-                .iter()
-                .map(|next| (valves.get(next).unwrap().clone(), 1))
-                .collect::<Vec<_>>()
-        }) {
-            map.insert((start.name.clone(), dest.name.clone()), distance);
-            map.insert((dest.name.clone(), start.name.clone()), distance);
-        }
-    }
-    map
+/// The most pressure that can be released for each set of opened valves.
+type BestPerMask = HashMap<u64, u32>;
+
+/// Someone opening valves, like you or the elephant.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Agent<'a> {
+    pub start: &'a str,
+    pub time: u32,
 }
 
-#[derive(Debug, Clone)]
-struct State {
-    time: u32,
-    max_time: u32,
-    position: String,
-    open_valves: HashSet<String>,
-    total_flow_rate: u32,
-    released_pressure: u32,
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Opening {
+    pub valve: String,
+    /// The (1-based) minute during which the valve is opened.
+    pub minute: u32,
+    /// The pressure this valve releases until the time is up.
+    pub released: u32,
+}
+
+/// The valves an agent should open, in order.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Schedule {
+    pub released: u32,
+    pub openings: Vec<Opening>,
+}
+
+/// The best way for a group of agents to release pressure together.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Plan {
+    pub released: u32,
+    /// The schedule of each agent, in the same order as the agents.
+    pub schedules: Vec<Schedule>,
 }
 
-impl State {
-    fn new(position: String, max_time: u32) -> Self {
+impl Network {
+    /// Interns the valves, and computes the distances between them using Floyd–Warshall.
+    ///
+    /// Panics if there are more than 64 working valves, see `input_generator()`.
+    pub fn new(valves: &[Valve]) -> Self {
+        let mut valves = valves.iter().collect::<Vec<_>>();
+        // Stable, so the working valves keep the order of the input.
+        valves.sort_by_key(|valve| valve.flow_rate == 0);
+        let working = valves.iter().filter(|valve| valve.flow_rate > 0).count();
+        assert!(working <= 64, "too many working valves");
+        let indices = valves
+            .iter()
+            .enumerate()
+            .map(|(i, valve)| (valve.name.as_str(), i))
+            .collect::<HashMap<_, _>>();
+        const UNREACHABLE: u32 = u32::MAX / 2;
+        let mut distances = vec![vec![UNREACHABLE; valves.len()]; valves.len()];
+        for (i, valve) in valves.iter().enumerate() {
+            distances[i][i] = 0;
+            for tunnel in &valve.tunnels {
+                distances[i][indices[tunnel.as_str()]] = 1;
+            }
+        }
+        for k in 0..valves.len() {
+            for i in 0..valves.len() {
+                for j in 0..valves.len() {
+                    let distance = distances[i][k] + distances[k][j];
+                    if distance < distances[i][j] {
+                        distances[i][j] = distance;
+                    }
+                }
+            }
+        }
+        // Only walking to working valves is worth it.
+        for row in &mut distances {
+            row.truncate(working);
+        }
         Self {
-            time: 0,
-            max_time,
-            position,
-            open_valves: HashSet::new(),
-            total_flow_rate: 0,
-            released_pressure: 0,
+            names: valves.iter().map(|valve| valve.name.clone()).collect(),
+            flow_rates: valves[..working]
+                .iter()
+                .map(|valve| valve.flow_rate)
+                .collect(),
+            distances,
         }
     }
 
-    fn successors(&self, valves: &HashMap<String, Valve>, distances: &DistanceMap) -> Vec<State> {
-        let mut successors = Vec::new();
-        if self.time == self.max_time {
-            // Time's up! No more steps.
-            return successors;
+    /// Panics if there is no such valve.
+    fn index(&self, name: &str) -> usize {
+        self.names
+            .iter()
+            .position(|n| n == name)
+            .unwrap_or_else(|| panic!("unknown valve `{}`", name))
+    }
+
+    /// The names of the valves that are open in the mask.
+    pub fn open_valves(&self, mask: u64) -> Vec<&str> {
+        (0..self.flow_rates.len())
+            .filter(|&i| mask & (1 << i) != 0)
+            .map(|i| self.names[i].as_str())
+            .collect()
+    }
+
+    /// Finds the most pressure that can be released in the given time for every set of
+    /// valves that can be opened in that time.
+    pub fn best_per_mask(&self, start: &str, time: u32) -> BestPerMask {
+        let mut best = BestPerMask::new();
+        let mut seen = HashMap::new();
+        self.explore(self.index(start), 0, time, 0, &mut seen, &mut best);
+        best
+    }
+
+    /// Searches every order of opening valves, skipping states `(valve, mask, time_left)`
+    /// that were already reached while releasing at least as much pressure.
+    fn explore(
+        &self,
+        valve: usize,
+        mask: u64,
+        time_left: u32,
+        pressure: u32,
+        seen: &mut HashMap<(usize, u64, u32), u32>,
+        best: &mut BestPerMask,
+    ) {
+        let best_for_mask = best.entry(mask).or_insert(0);
+        *best_for_mask = (*best_for_mask).max(pressure);
+        match seen.entry((valve, mask, time_left)) {
+            Entry::Occupied(entry) if *entry.get() >= pressure => return,
+            Entry::Occupied(mut entry) => {
+                entry.insert(pressure);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(pressure);
+            }
         }
-        // Move to closed valve and open it
-        let closed_valves = valves
-            .keys()
-            .filter(|&name| !self.open_valves.contains(name));
-        successors.extend(closed_valves.filter_map(|valve_name| {
-            let valve = valves.get(valve_name).unwrap();
-            if valve.flow_rate == 0 {
-                // No point in opening this valve
-                return None;
+        for (next, &flow_rate) in self.flow_rates.iter().enumerate() {
+            if mask & (1 << next) != 0 {
+                continue;
             }
-            // N time steps to move to valve
-            let distance = *distances
-                .get(&(self.position.clone(), valve_name.clone()))
-                .unwrap();
-            // 1 time step to open it
-            let distance = distance + 1;
-            if self.time + distance >= self.max_time {
-                // Can't get to valve in time
-                return None;
+            // Walk to the valve, and 1 minute to open it.
+            let cost = self.distances[valve][next] + 1;
+            if cost >= time_left {
+                // Opening it now wouldn't release any pressure.
+                continue;
             }
-            let mut next = self.clone();
-            next.time += distance;
-            next.released_pressure += distance * next.total_flow_rate;
-            next.position = valve_name.clone();
-            next.open_valves.insert(valve_name.clone());
-            next.total_flow_rate += valve.flow_rate;
-            Some(next)
-        }));
-        // Otherwise, stay here indefinitely
-        if successors.is_empty() {
-            successors.push({
-                let remaining_time = self.max_time - self.time;
-                let mut next = self.clone();
-                next.time += remaining_time;
-                next.released_pressure += remaining_time * next.total_flow_rate;
-                next
-            });
+            let time_left = time_left - cost;
+            self.explore(
+                next,
+                mask | (1 << next),
+                time_left,
+                pressure + flow_rate * time_left,
+                seen,
+                best,
+            );
         }
-        successors
     }
-}
 
-fn solve(valves: &HashMap<String, Valve>, max_time: u32) -> Vec<State> {
-    let distances = make_distance_map(valves);
-    let start_state = State::new("AA".to_string(), max_time);
-    let mut queue = Vec::<State>::new();
-    queue.push(start_state);
-    let mut solutions = Vec::<State>::new();
-    while let Some(state) = queue.pop() {
-        for next in state.successors(valves, &distances) {
-            if next.time == max_time {
-                // If new solution is not better for this set of valves, drop it
-                if let Some(best_for_valves) =
-                    solutions.iter().find(|x| x.open_valves == next.open_valves)
-                {
-                    if best_for_valves.released_pressure > next.released_pressure {
-                        continue;
-                    }
-                }
-                // Remove other solutions which are less optimal than the new solution
-                solutions.retain(|x| {
-                    next.open_valves != x.open_valves
-                        || next.released_pressure <= x.released_pressure
+    /// Finds the best order to open exactly the valves in the mask.
+    pub fn schedule(&self, start: &str, time: u32, mask: u64) -> Option<Schedule> {
+        let mut openings = Vec::new();
+        let mut best = None;
+        self.schedule_inner(
+            self.index(start),
+            mask,
+            time,
+            time,
+            &mut openings,
+            &mut best,
+        );
+        best
+    }
+
+    fn schedule_inner(
+        &self,
+        valve: usize,
+        remaining: u64,
+        time: u32,
+        time_left: u32,
+        openings: &mut Vec<Opening>,
+        best: &mut Option<Schedule>,
+    ) {
+        if remaining == 0 {
+            let released = openings.iter().map(|opening| opening.released).sum();
+            if best.as_ref().map_or(true, |best| released > best.released) {
+                *best = Some(Schedule {
+                    released,
+                    openings: openings.clone(),
                 });
-                // Add new solution
-                solutions.push(next);
-            } else {
-                queue.push(next);
             }
+            return;
+        }
+        for next in (0..self.flow_rates.len()).filter(|&i| remaining & (1 << i) != 0) {
+            let cost = self.distances[valve][next] + 1;
+            if cost >= time_left {
+                continue;
+            }
+            let time_left = time_left - cost;
+            openings.push(Opening {
+                valve: self.names[next].clone(),
+                minute: time - time_left,
+                released: self.flow_rates[next] * time_left,
+            });
+            self.schedule_inner(
+                next,
+                remaining & !(1 << next),
+                time,
+                time_left,
+                openings,
+                best,
+            );
+            openings.pop();
+        }
+    }
+
+    /// Finds the best way for the agents to release pressure together,
+    /// each opening a different set of valves.
+    ///
+    /// Panics if an agent starts at an unknown valve.
+    pub fn plan(&self, agents: &[Agent]) -> Plan {
+        // Agents with the same start and time budget (like you and the elephant)
+        // can share their solutions.
+        let mut unique = Vec::new();
+        for agent in agents {
+            if !unique.contains(agent) {
+                unique.push(*agent);
+            }
+        }
+        #[cfg(not(feature = "parallel"))]
+        let unique_iter = unique.iter();
+        #[cfg(feature = "parallel")]
+        let unique_iter = unique.par_iter();
+        let unique_solutions = unique_iter
+            .map(|agent| {
+                let mut solutions = self
+                    .best_per_mask(agent.start, agent.time)
+                    .into_iter()
+                    .collect::<Vec<_>>();
+                solutions.sort_unstable_by_key(|&(_, pressure)| Reverse(pressure));
+                solutions
+            })
+            .collect::<Vec<_>>();
+        let solutions = agents
+            .iter()
+            .map(|agent| {
+                let i = unique.iter().position(|other| other == agent).unwrap();
+                unique_solutions[i].as_slice()
+            })
+            .collect::<Vec<_>>();
+        // The most the remaining agents could release, ignoring overlaps.
+        let mut bounds = vec![0; agents.len() + 1];
+        for i in (0..agents.len()).rev() {
+            bounds[i] = bounds[i + 1] + solutions[i][0].1;
+        }
+        let mut masks = Vec::new();
+        // Opening nothing at all is always possible.
+        let mut best = (0, vec![0; agents.len()]);
+        assign(&solutions, &bounds, 0, 0, &mut masks, &mut best);
+
+        let (released, masks) = best;
+        let schedules = agents
+            .iter()
+            .zip(masks)
+            .map(|(agent, mask)| self.schedule(agent.start, agent.time, mask).unwrap())
+            .collect();
+        Plan {
+            released,
+            schedules,
         }
     }
-    solutions
+}
+
+/// Picks a disjoint set of valves for each agent, using branch and bound.
+fn assign(
+    solutions: &[&[(u64, u32)]],
+    bounds: &[u32],
+    used: u64,
+    released: u32,
+    masks: &mut Vec<u64>,
+    best: &mut (u32, Vec<u64>),
+) {
+    let agent = masks.len();
+    if agent == solutions.len() {
+        if released > best.0 {
+            *best = (released, masks.clone());
+        }
+        return;
+    }
+    for &(mask, pressure) in solutions[agent] {
+        if released + pressure + bounds[agent + 1] <= best.0 {
+            // Sorted by pressure, so the remaining solutions can't be better either.
+            break;
+        }
+        if mask & used != 0 {
+            continue;
+        }
+        masks.push(mask);
+        assign(
+            solutions,
+            bounds,
+            used | mask,
+            released + pressure,
+            masks,
+            best,
+        );
+        masks.pop();
+    }
 }
 
 #[aoc(day16, part1)]
 pub fn part1(input: &[Valve]) -> u32 {
-    let valves = input
-        .iter()
-        .cloned()
-        .map(|valve| (valve.name.clone(), valve))
-        .collect::<HashMap<_, _>>();
-    let max_time = 30;
-    let solutions = solve(&valves, max_time);
-    solutions
-        .iter()
-        .map(|state| state.released_pressure)
-        .max()
-        .unwrap()
+    let you = Agent {
+        start: "AA",
+        time: 30,
+    };
+    Network::new(input).plan(&[you]).released
 }
 
 #[aoc(day16, part2)]
 pub fn part2(input: &[Valve]) -> u32 {
-    let valves = input
-        .iter()
-        .cloned()
-        .map(|valve| (valve.name.clone(), valve))
-        .collect::<HashMap<_, _>>();
-    let max_time = 26;
-    let solutions = solve(&valves, max_time);
-    // Trick: even with two actors, we can never open all valves
-    // The best solution is for you and the elephant to only open *distinct* valves
-    let mut best = 0;
-    for (i, left) in solutions.iter().enumerate() {
-        for right in &solutions[(i + 1)..] {
-            // You and the elephant should never open the same valves
-            if left.open_valves.intersection(&right.open_valves).count() == 0 {
-                // If there are no overlapping valves, then we can safely add up the released pressures
-                let released_pressure = left.released_pressure + right.released_pressure;
-                best = best.max(released_pressure)
-            }
-        }
-    }
-    best
+    let you = Agent {
+        start: "AA",
+        time: 26,
+    };
+    let elephant = you;
+    Network::new(input).plan(&[you, elephant]).released
 }
 
 #[cfg(test)]
@@ -214,15 +417,129 @@ Valve JJ has flow rate=21; tunnel leads to valve II"
 
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part1(&input), 1651);
     }
 
-    // Trick doesn't work on the example input... :-(
     #[test]
-    #[ignore]
     fn test_part2() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part2(&input), 1707);
     }
+
+    fn valves(schedule: &Schedule) -> Vec<(&str, u32)> {
+        schedule
+            .openings
+            .iter()
+            .map(|opening| (opening.valve.as_str(), opening.minute))
+            .collect()
+    }
+
+    #[test]
+    fn test_plan() {
+        let input = input_generator(&TEST_INPUT).unwrap();
+        let network = Network::new(&input);
+        let you = Agent {
+            start: "AA",
+            time: 30,
+        };
+        let plan = network.plan(&[you]);
+        assert_eq!(plan.released, 1651);
+        assert_eq!(
+            valves(&plan.schedules[0]),
+            vec![
+                ("DD", 2),
+                ("BB", 5),
+                ("JJ", 9),
+                ("HH", 17),
+                ("EE", 21),
+                ("CC", 24)
+            ]
+        );
+        assert_eq!(plan.schedules[0].openings[0].released, 20 * 28);
+
+        let you = Agent {
+            start: "AA",
+            time: 26,
+        };
+        let plan = network.plan(&[you, you]);
+        assert_eq!(plan.released, 1707);
+        let mut schedules = plan.schedules.iter().map(valves).collect::<Vec<_>>();
+        schedules.sort_unstable();
+        assert_eq!(
+            schedules,
+            vec![
+                vec![("DD", 2), ("HH", 7), ("EE", 11)],
+                vec![("JJ", 3), ("BB", 7), ("CC", 9)],
+            ]
+        );
+
+        // Three agents that start in different places, with different time budgets.
+        let agents = [
+            Agent {
+                start: "HH",
+                time: 5,
+            },
+            Agent {
+                start: "JJ",
+                time: 5,
+            },
+            Agent {
+                start: "AA",
+                time: 3,
+            },
+        ];
+        let plan = network.plan(&agents);
+        let schedules = plan.schedules.iter().map(valves).collect::<Vec<_>>();
+        assert_eq!(
+            schedules,
+            vec![vec![("HH", 1)], vec![("JJ", 1)], vec![("DD", 2)]]
+        );
+        assert_eq!(plan.released, 22 * 4 + 21 * 4 + 20);
+    }
+
+    #[test]
+    fn test_best_per_mask() {
+        let input = input_generator(&TEST_INPUT).unwrap();
+        let network = Network::new(&input);
+        let best = network.best_per_mask("AA", 30);
+        let (&mask, &pressure) = best.iter().max_by_key(|&(_, &pressure)| pressure).unwrap();
+        assert_eq!(pressure, 1651);
+        let mut open_valves = network.open_valves(mask);
+        open_valves.sort_unstable();
+        assert_eq!(open_valves, vec!["BB", "CC", "DD", "EE", "HH", "JJ"]);
+    }
+
+    #[test]
+    fn test_many_valves() {
+        // A ring of 64 working valves through the start, each 1 minute from the next.
+        let mut input = "Valve AA has flow rate=0; tunnels lead to valves V0, V63\n".to_string();
+        for i in 0..64 {
+            let previous = if i == 0 {
+                "AA".to_string()
+            } else {
+                format!("V{}", i - 1)
+            };
+            let next = if i == 63 {
+                "AA".to_string()
+            } else {
+                format!("V{}", i + 1)
+            };
+            input += &format!(
+                "Valve V{} has flow rate=1; tunnels lead to valves {}, {}\n",
+                i, previous, next
+            );
+        }
+        let valves = input_generator(input.trim()).unwrap();
+        let network = Network::new(&valves);
+        let best = network.best_per_mask("AA", 8);
+        // Open the first 3 valves in either direction: 6 + 4 + 2.
+        assert_eq!(best.values().max(), Some(&12));
+        assert_eq!(network.open_valves(1 << 63), vec!["V63"]);
+        assert_eq!(best.get(&(1 << 63 | 1 << 62 | 1 << 61)), Some(&12));
+
+        let input = input + "Valve XX has flow rate=1; tunnel leads to valve AA";
+        let err = input_generator(&input).unwrap_err();
+        assert_eq!((err.line, err.column), (66, 1));
+    }
 }
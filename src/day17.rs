@@ -1,6 +1,8 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 
-use crate::util::Vector2D;
+use crate::util::{blocks, Cycle, Line, ParseError};
 
 #[derive(Debug, Copy, Clone)]
 pub enum Jet {
@@ -9,218 +11,361 @@ pub enum Jet {
 }
 
 #[aoc_generator(day17)]
-pub fn input_generator(input: &str) -> Vec<Jet> {
-    input
-        .trim()
-        .chars()
-        .map(|c| match c {
-            '<' => Jet::Left,
-            '>' => Jet::Right,
-            c => panic!("invalid jet: {}", c),
+pub fn input_generator(input: &str) -> Result<Vec<Jet>, ParseError> {
+    let line = Line::new(17, 1, input.trim());
+    line.text
+        .char_indices()
+        .map(|(x, c)| match c {
+            '<' => Ok(Jet::Left),
+            '>' => Ok(Jet::Right),
+            _ => Err(line.error(&line.text[x..], "invalid jet")),
         })
         .collect()
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-enum RockType {
-    One,
-    Two,
-    Three,
-    Four,
-    Five,
-}
-
-// X is left to right
-// Y is bottom to top
-const ROCK1: [Vector2D; 4] = [
-    Vector2D::new(0, 0),
-    Vector2D::new(1, 0),
-    Vector2D::new(2, 0),
-    Vector2D::new(3, 0),
-];
-const ROCK2: [Vector2D; 5] = [
-    Vector2D::new(1, 0),
-    Vector2D::new(0, 1),
-    Vector2D::new(1, 1),
-    Vector2D::new(2, 1),
-    Vector2D::new(1, 2),
-];
-const ROCK3: [Vector2D; 5] = [
-    Vector2D::new(0, 0),
-    Vector2D::new(1, 0),
-    Vector2D::new(2, 0),
-    Vector2D::new(2, 1),
-    Vector2D::new(2, 2),
-];
-const ROCK4: [Vector2D; 4] = [
-    Vector2D::new(0, 0),
-    Vector2D::new(0, 1),
-    Vector2D::new(0, 2),
-    Vector2D::new(0, 3),
-];
-const ROCK5: [Vector2D; 4] = [
-    Vector2D::new(0, 0),
-    Vector2D::new(1, 0),
-    Vector2D::new(0, 1),
-    Vector2D::new(1, 1),
-];
-
-impl RockType {
-    fn all() -> [RockType; 5] {
-        [
-            RockType::One,
-            RockType::Two,
-            RockType::Three,
-            RockType::Four,
-            RockType::Five,
-        ]
-    }
-
-    fn to_blocks(self) -> &'static [Vector2D] {
+/// The rocks from the puzzle, in the order in which they fall.
+pub const DEFAULT_ROCKS: &str = "\
+####
+
+.#.
+###
+.#.
+
+..#
+..#
+###
+
+#
+#
+#
+#
+
+##
+##";
+
+/// A rock shape, as one bitmask per row.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Rock {
+    /// Bottom to top. Bit `x` is set if the rock has a block `x` columns from its left edge.
+    rows: Vec<u64>,
+    width: u32,
+}
+
+impl Rock {
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+}
+
+/// Parses rock shapes in the puzzle's notation, separated by blank lines.
+///
+/// Empty rows and columns around a shape are ignored.
+pub fn parse_rocks(input: &str) -> Result<Vec<Rock>, ParseError> {
+    blocks(17, input)
+        .into_iter()
+        .map(|block| {
+            let mut rows = Vec::new();
+            for line in &block {
+                let mut row = 0u64;
+                for (x, c) in line.text.char_indices() {
+                    match c {
+                        '#' if x >= 64 => {
+                            return Err(line.error(&line.text[x..], "rock is too wide"))
+                        }
+                        '#' => row |= 1 << x,
+                        '.' => {}
+                        _ => return Err(line.error(&line.text[x..], "invalid rock tile")),
+                    }
+                }
+                rows.push(row);
+            }
+            // Rows are written top to bottom
+            rows.reverse();
+            while rows.last() == Some(&0) {
+                rows.pop();
+            }
+            while rows.first() == Some(&0) {
+                rows.remove(0);
+            }
+            let all = rows.iter().fold(0, |all, row| all | row);
+            if all == 0 {
+                return Err(block[0].error(block[0].text, "rock has no blocks"));
+            }
+            let left = all.trailing_zeros();
+            Ok(Rock {
+                rows: rows.iter().map(|row| row >> left).collect(),
+                width: 64 - (all >> left).leading_zeros(),
+            })
+        })
+        .collect()
+}
+
+pub fn default_rocks() -> Vec<Rock> {
+    parse_rocks(DEFAULT_ROCKS).unwrap()
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ChamberError {
+    InvalidWidth(u32),
+    /// The rock with this index doesn't fit next to the 2 units of space on its left.
+    RockTooWide(usize),
+    NoRocks,
+    NoJets,
+    /// The tower never settles into a repeating pattern near its top.
+    NoCycle,
+}
+
+impl Display for ChamberError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            RockType::One => &ROCK1,
-            RockType::Two => &ROCK2,
-            RockType::Three => &ROCK3,
-            RockType::Four => &ROCK4,
-            RockType::Five => &ROCK5,
+            ChamberError::InvalidWidth(width) => {
+                write!(f, "chamber width {} is not between 1 and 64", width)
+            }
+            ChamberError::RockTooWide(index) => {
+                write!(f, "rock {} does not fit in the chamber", index + 1)
+            }
+            ChamberError::NoRocks => write!(f, "there are no rocks to drop"),
+            ChamberError::NoJets => write!(f, "there are no jets of hot gas"),
+            ChamberError::NoCycle => write!(f, "the tower does not grow in cycles"),
         }
     }
 }
 
-#[derive(Debug, Default)]
-struct Tower {
-    height: i32,
-    blocks: HashSet<Vector2D>,
+impl Error for ChamberError {}
+
+/// A tall, narrow chamber that rocks fall into, pushed around by jets of hot gas.
+///
+/// Only the rows that falling rocks can still reach are kept in memory,
+/// everything below them is treated as solid rock.
+#[derive(Debug, Clone)]
+pub struct Chamber<'a> {
+    width: u32,
+    rocks: &'a [Rock],
+    jets: &'a [Jet],
+    rock_index: usize,
+    jet_index: usize,
+    /// The y coordinate of `rows[0]`.
+    floor: i64,
+    /// Bottom to top. Bit `x` is set if there is a block in column `x`.
+    /// Never contains empty rows at the top.
+    rows: VecDeque<u64>,
 }
 
-impl Tower {
-    fn overlaps(&self, rock: RockType, pos: Vector2D) -> bool {
-        for block in rock.to_blocks() {
-            let block = *block + pos;
-            if block.x() < 0 || block.x() >= 7 || block.y() < 0 || self.blocks.contains(&block) {
-                return true;
-            }
+impl<'a> Chamber<'a> {
+    pub fn new(width: u32, rocks: &'a [Rock], jets: &'a [Jet]) -> Result<Self, ChamberError> {
+        if !(1..=64).contains(&width) {
+            return Err(ChamberError::InvalidWidth(width));
+        }
+        if rocks.is_empty() {
+            return Err(ChamberError::NoRocks);
+        }
+        if jets.is_empty() {
+            return Err(ChamberError::NoJets);
+        }
+        if let Some(index) = rocks.iter().position(|rock| rock.width + 2 > width) {
+            return Err(ChamberError::RockTooWide(index));
+        }
+        Ok(Self {
+            width,
+            rocks,
+            jets,
+            rock_index: 0,
+            jet_index: 0,
+            floor: 0,
+            rows: VecDeque::new(),
+        })
+    }
+
+    pub fn height(&self) -> i64 {
+        self.floor + self.rows.len() as i64
+    }
+
+    /// The number of rows currently kept in memory.
+    pub fn rows_in_memory(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn full_row(&self) -> u64 {
+        u64::MAX >> (64 - self.width)
+    }
+
+    fn row(&self, y: i64) -> u64 {
+        if y < self.floor {
+            self.full_row()
+        } else {
+            self.rows
+                .get((y - self.floor) as usize)
+                .copied()
+                .unwrap_or(0)
         }
-        false
     }
 
-    fn place_rock(&mut self, rock: RockType, pos: Vector2D) {
-        for block in rock.to_blocks() {
-            let block = *block + pos;
-            self.height = self.height.max(block.y() + 1);
-            self.blocks.insert(block);
+    fn overlaps(&self, rock: &Rock, x: i64, y: i64) -> bool {
+        if x < 0 || x + rock.width as i64 > self.width as i64 {
+            return true;
         }
+        rock.rows
+            .iter()
+            .enumerate()
+            .any(|(dy, row)| self.row(y + dy as i64) & (row << x) != 0)
     }
 
-    fn drop_rock(&self, rock: RockType, jets: &mut impl Iterator<Item = (usize, Jet)>) -> Vector2D {
-        let mut pos = Vector2D::new(2, self.height + 3);
+    /// Drops the next rock, and returns the y coordinate where it landed.
+    pub fn drop_next(&mut self) -> i64 {
+        let rock = &self.rocks[self.rock_index];
+        self.rock_index = (self.rock_index + 1) % self.rocks.len();
+        let (mut x, mut y) = (2, self.height() + 3);
         loop {
             // Push left or right, if possible
-            let (_, jet) = jets.next().unwrap();
-            let next_pos = match jet {
-                Jet::Left => pos + Vector2D::new(-1, 0),
-                Jet::Right => pos + Vector2D::new(1, 0),
+            let jet = self.jets[self.jet_index];
+            self.jet_index = (self.jet_index + 1) % self.jets.len();
+            let next_x = match jet {
+                Jet::Left => x - 1,
+                Jet::Right => x + 1,
             };
-            if !self.overlaps(rock, next_pos) {
-                pos = next_pos;
+            if !self.overlaps(rock, next_x, y) {
+                x = next_x;
             }
             // Drop down
-            let next_pos = pos + Vector2D::new(0, -1);
-            if self.overlaps(rock, next_pos) {
+            if self.overlaps(rock, x, y - 1) {
                 // Landed
-                return pos;
+                break;
             }
-            pos = next_pos;
+            y -= 1;
+        }
+        for (dy, row) in rock.rows.iter().enumerate() {
+            let index = (y + dy as i64 - self.floor) as usize;
+            if index >= self.rows.len() {
+                self.rows.resize(index + 1, 0);
+            }
+            self.rows[index] |= row << x;
+        }
+        self.discard_unreachable();
+        y
+    }
+
+    /// The cells that falling rocks can still reach, as one bitmask per row,
+    /// from the top row of the tower down to the lowest row with a reachable cell.
+    ///
+    /// Rocks only move left, right and down, so every block of a rock has followed
+    /// such a path through empty space from above the tower.
+    /// Any empty cell to the left, right or below a reachable cell is reachable too,
+    /// so this also determines which cells a rock can bump into: all the others.
+    pub fn surface(&self) -> Vec<u64> {
+        self.surface_to_depth(self.rows.len())
+    }
+
+    /// Like `surface`, but only the top `depth` rows.
+    fn surface_to_depth(&self, depth: usize) -> Vec<u64> {
+        let full = self.full_row();
+        let mut surface = Vec::new();
+        let mut reachable = full;
+        for row in self.rows.iter().rev().take(depth) {
+            let empty = !row & full;
+            reachable &= empty;
+            // Spread left and right through the empty cells of the row
+            loop {
+                let spread = reachable | ((reachable << 1 | reachable >> 1) & empty);
+                if spread == reachable {
+                    break;
+                }
+                reachable = spread;
+            }
+            if reachable == 0 {
+                break;
+            }
+            surface.push(reachable);
+        }
+        surface
+    }
+
+    /// Discards the rows below the surface, except for the row right below it,
+    /// which rocks can still land on.
+    fn discard_unreachable(&mut self) {
+        let depth = self.surface().len() + 1;
+        if depth < self.rows.len() {
+            let discarded = self.rows.len() - depth;
+            self.rows.drain(..discarded);
+            self.floor += discarded as i64;
         }
     }
 }
 
-#[aoc(day17, part1)]
-pub fn part1(input: &[Jet]) -> i32 {
-    let mut tower = Tower::default();
-    let mut rocks = RockType::all().into_iter().cycle();
-    let mut jets = input.iter().cloned().enumerate().cycle();
-    for _ in 0..2022 {
-        let rock = rocks.next().unwrap();
-        let pos = tower.drop_rock(rock, &mut jets);
-        tower.place_rock(rock, pos);
+/// The deepest surface that `tower_height` looks at, before giving up on finding a cycle.
+const MAX_SURFACE_DEPTH: usize = 1 << 12;
+
+/// Computes the height of the tower after `count` rocks have fallen.
+///
+/// Looks for a cycle in the top of the surface of the tower. Rocks that only ever
+/// land within that part of the surface can't tell the rest apart, so if that's true
+/// for every rock in the cycle, it repeats forever. Otherwise, it tries again with
+/// a deeper part of the surface.
+pub fn tower_height(
+    width: u32,
+    rocks: &[Rock],
+    jets: &[Jet],
+    count: u64,
+) -> Result<i64, ChamberError> {
+    let chamber = Chamber::new(width, rocks, jets)?;
+    let mut depth = 32;
+    while depth <= MAX_SURFACE_DEPTH {
+        // How many rows below the top of the tower each rock bumped into something
+        let mut reach = Vec::new();
+        let cycle = Cycle::find_by_key(
+            chamber.clone(),
+            |chamber| {
+                let height = chamber.height();
+                let y = chamber.drop_next();
+                reach.push((height - y + 1) as usize);
+            },
+            |chamber| chamber.capture(depth),
+            Chamber::height,
+        );
+        let cycle_reach = &reach[cycle.prefix..cycle.prefix + cycle.length];
+        if cycle_reach.iter().all(|&reach| reach <= depth) {
+            return Ok(cycle.extrapolate(count));
+        }
+        depth *= 2;
     }
-    tower.height
+    Err(ChamberError::NoCycle)
 }
 
-fn find_deepest_drop(jets: &[Jet], cycles: usize) -> i32 {
-    let mut tower = Tower::default();
-    let mut rocks = RockType::all().into_iter().cycle();
-    let mut jets = jets.iter().cloned().enumerate().cycle();
-    let mut deepest = 0;
-    for _ in 0..cycles {
-        let rock = rocks.next().unwrap();
-        let pos = tower.drop_rock(rock, &mut jets);
-        tower.place_rock(rock, pos);
-        deepest = deepest.max(tower.height - pos.y());
+#[aoc(day17, part1)]
+pub fn part1(input: &[Jet]) -> Result<i64, ChamberError> {
+    let rocks = default_rocks();
+    let mut chamber = Chamber::new(7, &rocks, input)?;
+    for _ in 0..2022 {
+        chamber.drop_next();
     }
-    deepest
+    Ok(chamber.height())
 }
 
+/// Everything that determines how the tower grows from here on,
+/// as long as the rocks don't fall deeper than the captured surface.
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 struct State {
     rock_index: usize,
     jet_index: usize,
-    top: Vec<Vector2D>,
+    surface: Vec<u64>,
 }
 
-impl Tower {
-    fn capture(&self, rock_index: usize, jet_index: usize, depth: i32) -> State {
-        let mut top = Vec::new();
-        for y in (-depth)..=0 {
-            for x in 0..7 {
-                if self.blocks.contains(&Vector2D::new(x, self.height + y)) {
-                    top.push(Vector2D::new(x, y));
-                }
-            }
-        }
+impl Chamber<'_> {
+    fn capture(&self, depth: usize) -> State {
         State {
-            rock_index,
-            jet_index,
-            top,
+            rock_index: self.rock_index,
+            jet_index: self.jet_index,
+            surface: self.surface_to_depth(depth),
         }
     }
 }
 
 #[aoc(day17, part2)]
-pub fn part2(input: &[Jet]) -> u64 {
-    let mut tower = Tower::default();
-    let mut rocks = RockType::all().into_iter().enumerate().cycle().peekable();
-    let mut jets = input.iter().cloned().enumerate().cycle().peekable();
-    let cache_depth = find_deepest_drop(input, 1000);
-    let target = 1_000_000_000_000u64;
-    let mut cache = HashMap::<State, (u64, i32)>::new();
-    // Find first cycle
-    let mut i = 0;
-    let (cycle_length, cycle_height) = loop {
-        let (rock_index, _) = *rocks.peek().unwrap();
-        let (jet_index, _) = *jets.peek().unwrap();
-        let state = tower.capture(rock_index, jet_index, cache_depth);
-        if let Some(&(previous_i, previous_height)) = cache.get(&state) {
-            break (i - previous_i, tower.height - previous_height);
-        }
-        cache.insert(state, (i, tower.height));
-        let (_, rock) = rocks.next().unwrap();
-        let pos = tower.drop_rock(rock, &mut jets);
-        tower.place_rock(rock, pos);
-        i += 1;
-    };
-    // Skip over repeated cycles
-    let cycle_count = (target - i) / cycle_length;
-    i += cycle_count * cycle_length;
-    // Finish remaining drops
-    while i < target {
-        let (_, rock) = rocks.next().unwrap();
-        let pos = tower.drop_rock(rock, &mut jets);
-        tower.place_rock(rock, pos);
-        i += 1;
-    }
-    tower.height as u64 + (cycle_count * cycle_height as u64)
+pub fn part2(input: &[Jet]) -> Result<u64, ChamberError> {
+    tower_height(7, &default_rocks(), input, 1_000_000_000_000).map(|height| height as u64)
 }
 
 #[cfg(test)]
@@ -233,13 +378,132 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
-        assert_eq!(part1(&input), 3068);
+        let input = input_generator(&TEST_INPUT).unwrap();
+        assert_eq!(part1(&input), Ok(3068));
     }
 
     #[test]
     fn test_part2() {
-        let input = input_generator(&TEST_INPUT);
-        assert_eq!(part2(&input), 1_514_285_714_288);
+        let input = input_generator(&TEST_INPUT).unwrap();
+        assert_eq!(part2(&input), Ok(1_514_285_714_288));
+    }
+
+    #[test]
+    fn test_parse_rocks() {
+        let rocks = default_rocks();
+        assert_eq!(rocks.len(), 5);
+        assert_eq!(rocks[1].rows, [0b010, 0b111, 0b010]);
+        // The L shape is stored bottom to top
+        assert_eq!(rocks[2].rows, [0b111, 0b100, 0b100]);
+        assert_eq!((rocks[3].width(), rocks[3].height()), (1, 4));
+        let rocks = parse_rocks("...\n.##.\n..#.\n....\n").unwrap();
+        assert_eq!(
+            rocks,
+            [Rock {
+                rows: vec![0b10, 0b11],
+                width: 2
+            }]
+        );
+        let err = parse_rocks("##\n\n.#x").unwrap_err();
+        assert_eq!((err.line, err.column), (3, 3));
+        assert!(parse_rocks("..\n..").is_err());
+    }
+
+    #[test]
+    fn test_discards_unreachable_rows() {
+        let input = input_generator(&TEST_INPUT).unwrap();
+        let rocks = default_rocks();
+        let mut chamber = Chamber::new(7, &rocks, &input).unwrap();
+        for _ in 0..100_000 {
+            chamber.drop_next();
+            assert!(chamber.rows_in_memory() < 100);
+        }
+        assert_eq!(chamber.height(), 151_434);
+    }
+
+    #[test]
+    fn test_chamber_variants() {
+        let input = input_generator(&TEST_INPUT).unwrap();
+        let rocks = default_rocks();
+        assert_eq!(
+            Chamber::new(65, &rocks, &input).unwrap_err(),
+            ChamberError::InvalidWidth(65)
+        );
+        assert_eq!(
+            Chamber::new(5, &rocks, &input).unwrap_err(),
+            ChamberError::RockTooWide(0)
+        );
+        assert_eq!(
+            Chamber::new(7, &rocks, &[]).unwrap_err(),
+            ChamberError::NoJets
+        );
+        // A chamber as wide as the bit mask, with a single rock that fills all but 2 columns
+        let wide = parse_rocks(&"#".repeat(62)).unwrap();
+        let height = tower_height(64, &wide, &input, 3_000_000_000).unwrap();
+        assert_eq!(height, 3_000_000_000);
+        // Extrapolation agrees with simulating every rock in a narrower chamber
+        let mut chamber = Chamber::new(6, &rocks, &input).unwrap();
+        for _ in 0..100_000 {
+            chamber.drop_next();
+        }
+        assert_eq!(
+            tower_height(6, &rocks, &input, 100_000),
+            Ok(chamber.height())
+        );
+    }
+
+    #[test]
+    fn test_surface() {
+        let input = input_generator(&TEST_INPUT).unwrap();
+        let rocks = default_rocks();
+        let chamber = |rows: &[u64]| Chamber {
+            rows: rows.iter().copied().collect(),
+            ..Chamber::new(7, &rocks, &input).unwrap()
+        };
+        // The empty cell in the bottom left corner is sealed off by the block above it
+        let open = chamber(&[0b1111111, 0b0000001]);
+        let sealed = chamber(&[0b1111110, 0b0000001]);
+        assert_eq!(open.surface(), [0b1111110]);
+        assert_eq!(open.capture(10), sealed.capture(10));
+        // An overhang still lets rocks slide underneath it
+        let overhang = chamber(&[0b1111011, 0b0000011, 0b0111100]);
+        assert_eq!(overhang.surface(), [0b1000011, 0b1111100, 0b0000100]);
+        assert_ne!(
+            overhang.capture(10),
+            chamber(&[0b1111111, 0b0000011, 0b0111100]).capture(10)
+        );
+    }
+
+    #[test]
+    fn test_mirrored_jets() {
+        let mirrored = TEST_INPUT
+            .chars()
+            .map(|c| if c == '<' { '>' } else { '<' })
+            .collect::<String>();
+        let input = input_generator(&mirrored).unwrap();
+        let rocks = default_rocks();
+        let mut chamber = Chamber::new(7, &rocks, &input).unwrap();
+        for _ in 0..10_000 {
+            chamber.drop_next();
+        }
+        assert_eq!(
+            tower_height(7, &rocks, &input, 10_000),
+            Ok(chamber.height())
+        );
+    }
+
+    #[test]
+    fn test_open_well() {
+        // Every rock is pushed against the left wall, so the well on the right
+        // stays open all the way down to the floor.
+        let input = input_generator("<").unwrap();
+        let rocks = default_rocks();
+        let mut chamber = Chamber::new(7, &rocks, &input).unwrap();
+        for _ in 0..2_000 {
+            chamber.drop_next();
+        }
+        assert_eq!(chamber.surface().len(), 4_400);
+        assert_eq!(tower_height(7, &rocks, &input, 2_000), Ok(chamber.height()));
+        assert_eq!(part2(&input), Ok(2_200_000_000_000));
     }
 }
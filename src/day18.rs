@@ -1,17 +1,18 @@
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 
-use crate::util::Vector3D;
+use crate::util::{lines, BoundingBox, ParseError, Vector3D};
 
 #[aoc_generator(day18)]
-pub fn input_generator(input: &str) -> Vec<Vector3D> {
-    input
-        .lines()
+pub fn input_generator(input: &str) -> Result<Vec<Vector3D>, ParseError> {
+    lines(18, input)
         .map(|line| {
-            let coords = line
-                .split(',')
-                .map(|x| x.parse().unwrap())
-                .collect::<Vec<i32>>();
-            Vector3D::new(coords[0], coords[1], coords[2])
+            let (x, s) = line.split_once(line.text, ",")?;
+            let (y, z) = line.split_once(s, ",")?;
+            Ok(Vector3D::new(
+                line.parse(x)?,
+                line.parse(y)?,
+                line.parse(z)?,
+            ))
         })
         .collect()
 }
@@ -30,42 +31,369 @@ pub fn part1(input: &[Vector3D]) -> u64 {
     area
 }
 
-#[aoc(day18, part2)]
-pub fn part2(input: &[Vector3D]) -> u64 {
-    let max_coord = input
-        .iter()
-        .flat_map(|cube| cube.coords.into_iter())
-        .max()
-        .unwrap();
-    let cubes = input.iter().cloned().collect::<HashSet<_>>();
-    // Flood-fill to find all exterior cubes
-    let mut queue = VecDeque::from([Vector3D::zero()]);
-    let mut exterior = HashSet::new();
-    while let Some(pos) = queue.pop_front() {
-        for neighbour in pos.neighbours() {
-            if exterior.contains(&neighbour) || cubes.contains(&neighbour) {
-                // Already visited, or cube is internal
-            } else if neighbour
-                .coords
-                .iter()
-                .all(|&coord| coord >= -1 && coord <= max_coord + 1)
+/// The largest number of cells for which the exterior is stored as one bit per cell.
+const DENSE_LIMIT: i128 = 1 << 27;
+
+/// The air cells that are connected to the outside of the droplet.
+#[derive(Debug, Clone)]
+enum Exterior {
+    /// One bit per cell inside `bounds`, which has a margin of 1 around the droplet.
+    Dense {
+        bounds: BoundingBox<i32, 3>,
+        bits: Vec<u64>,
+    },
+    /// The droplet with the empty space between the coordinates the cubes use collapsed,
+    /// so that every cell of the compressed grid is either a single cube or a box of air.
+    /// See `Axis` for how the coordinates are compressed.
+    Sparse {
+        axes: [Axis; 3],
+        cells: HashSet<Vector3D>,
+    },
+}
+
+/// The intervals along one axis of the compressed grid.
+///
+/// Index `i` covers `starts[i]..starts[i + 1]`, and the indices `-1` and
+/// `starts.len() - 1` cover everything below and above the droplet.
+#[derive(Debug, Clone)]
+struct Axis {
+    starts: Vec<i32>,
+}
+
+impl Axis {
+    fn new(coords: impl Iterator<Item = i32>) -> Self {
+        // Every cube coordinate gets an interval of its own,
+        // and the space up to the next cube coordinate is a single interval too.
+        let mut starts = coords
+            .flat_map(|coord| [coord, coord.saturating_add(1)])
+            .collect::<Vec<_>>();
+        starts.sort_unstable();
+        starts.dedup();
+        Self { starts }
+    }
+
+    fn compress(&self, coord: i32) -> i32 {
+        self.starts.partition_point(|&start| start <= coord) as i32 - 1
+    }
+
+    /// The range of compressed indices, including the space around the droplet.
+    fn range(&self) -> (i32, i32) {
+        (-1, self.starts.len() as i32 - 1)
+    }
+}
+
+impl Exterior {
+    fn dense(cubes: &HashSet<Vector3D>, bounds: BoundingBox<i32, 3>) -> Self {
+        let mut bits = vec![0; (volume(&bounds) as usize + 63) / 64];
+        // Flood-fill from a corner, which is outside the droplet
+        let mut queue = VecDeque::from([bounds.min]);
+        set_bit(&mut bits, dense_index(&bounds, bounds.min));
+        while let Some(pos) = queue.pop_front() {
+            for neighbour in pos.neighbours() {
+                if bounds.contains(&neighbour)
+                    && !cubes.contains(&neighbour)
+                    && set_bit(&mut bits, dense_index(&bounds, neighbour))
+                {
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+        Exterior::Dense { bounds, bits }
+    }
+
+    fn sparse(cubes: &HashSet<Vector3D>) -> Self {
+        let axes = [0, 1, 2].map(|i| Axis::new(cubes.iter().map(|cube| cube.coords[i])));
+        let compressed_cubes = cubes
+            .iter()
+            .map(|cube| compress(&axes, cube))
+            .collect::<HashSet<_>>();
+        let ranges = [0, 1, 2].map(|i| axes[i].range());
+        let bounds = BoundingBox::new(
+            Vector3D::from(ranges.map(|(min, _)| min)),
+            Vector3D::from(ranges.map(|(_, max)| max)),
+        );
+        // Flood-fill the compressed grid, from a corner outside the droplet
+        let mut queue = VecDeque::from([bounds.min]);
+        let mut cells = HashSet::from([bounds.min]);
+        while let Some(pos) = queue.pop_front() {
+            for neighbour in pos.neighbours() {
+                if bounds.contains(&neighbour)
+                    && !compressed_cubes.contains(&neighbour)
+                    && cells.insert(neighbour)
+                {
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+        Exterior::Sparse { axes, cells }
+    }
+
+    fn contains(&self, pos: &Vector3D) -> bool {
+        match self {
+            Exterior::Dense { bounds, bits } => {
+                // Everything outside the margin is outside the droplet too
+                !bounds.contains(pos) || {
+                    let index = dense_index(bounds, *pos);
+                    bits[index / 64] & (1 << (index % 64)) != 0
+                }
+            }
+            Exterior::Sparse { axes, cells } => cells.contains(&compress(axes, pos)),
+        }
+    }
+}
+
+fn compress(axes: &[Axis; 3], pos: &Vector3D) -> Vector3D {
+    Vector3D::new(
+        axes[0].compress(pos.x()),
+        axes[1].compress(pos.y()),
+        axes[2].compress(pos.z()),
+    )
+}
+
+/// The number of cells in a box, which might not even fit in an `i64`.
+fn volume(bounds: &BoundingBox<i32, 3>) -> i128 {
+    (0..3)
+        .map(|i| bounds.max.coords[i] as i128 - bounds.min.coords[i] as i128 + 1)
+        .product()
+}
+
+fn dense_index(bounds: &BoundingBox<i32, 3>, pos: Vector3D) -> usize {
+    let size = [0, 1, 2].map(|i| (bounds.max.coords[i] - bounds.min.coords[i] + 1) as usize);
+    let offset = [0, 1, 2].map(|i| (pos.coords[i] - bounds.min.coords[i]) as usize);
+    (offset[2] * size[1] + offset[1]) * size[0] + offset[0]
+}
+
+/// Sets a bit, and returns whether it was not set yet.
+fn set_bit(bits: &mut [u64], index: usize) -> bool {
+    let mask = 1 << (index % 64);
+    let was_set = bits[index / 64] & mask != 0;
+    bits[index / 64] |= mask;
+    !was_set
+}
+
+/// A lava droplet, together with the steam that surrounds it.
+#[derive(Debug, Clone)]
+pub struct Droplet {
+    cubes: HashSet<Vector3D>,
+    exterior: Exterior,
+}
+
+/// A pocket of air that is trapped inside the droplet.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Pocket {
+    pub volume: usize,
+    pub bounds: BoundingBox<i32, 3>,
+}
+
+impl Droplet {
+    /// Finds the steam around the droplet, which may be anywhere in space.
+    ///
+    /// Droplets that fit in a small box are flood-filled cell by cell, others are
+    /// flood-filled with the empty space between their cubes collapsed.
+    pub fn new(cubes: &[Vector3D]) -> Self {
+        let bounds = BoundingBox::from_points(cubes.iter().cloned())
+            .unwrap_or_else(|| BoundingBox::from_point(Vector3D::zero()));
+        let cubes = cubes.iter().cloned().collect::<HashSet<_>>();
+        let margin = BoundingBox::new(
+            bounds.min.map(|&x| x.saturating_sub(1)),
+            bounds.max.map(|&x| x.saturating_add(1)),
+        );
+        let exterior = if volume(&margin) <= DENSE_LIMIT {
+            Exterior::dense(&cubes, margin)
+        } else {
+            Exterior::sparse(&cubes)
+        };
+        Self { cubes, exterior }
+    }
+
+    /// The area of the faces of the droplet that touch the steam outside.
+    pub fn exterior_area(&self) -> u64 {
+        let mut area = 0;
+        for cube in &self.cubes {
+            for neighbour in cube.neighbours() {
+                if self.exterior.contains(&neighbour) {
+                    area += 1;
+                }
+            }
+        }
+        area
+    }
+
+    /// Finds the connected pockets of air inside the droplet, ordered by their lowest corner.
+    pub fn pockets(&self) -> Vec<Pocket> {
+        let mut visited = HashSet::new();
+        let mut pockets = Vec::new();
+        // Every pocket touches at least one cube
+        let starts = self.cubes.iter().flat_map(|cube| cube.neighbours());
+        for start in starts {
+            if self.cubes.contains(&start)
+                || self.exterior.contains(&start)
+                || !visited.insert(start)
             {
-                // Expand steam around droplet
-                exterior.insert(neighbour);
-                queue.push_back(neighbour);
+                continue;
             }
+            let mut pocket = Pocket {
+                volume: 0,
+                bounds: BoundingBox::from_point(start),
+            };
+            let mut queue = VecDeque::from([start]);
+            while let Some(pos) = queue.pop_front() {
+                pocket.volume += 1;
+                pocket.bounds.extend(pos);
+                for neighbour in pos.neighbours() {
+                    if !self.cubes.contains(&neighbour) && visited.insert(neighbour) {
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+            pockets.push(pocket);
         }
+        pockets.sort_by_key(|pocket| pocket.bounds.min.coords);
+        pockets
     }
-    // Count the area
-    let mut area = 0;
-    for cube in &cubes {
-        for neighbour in cube.neighbours() {
-            if exterior.contains(&neighbour) {
-                area += 1;
+
+    /// Builds a mesh of the exterior surface of the droplet, where every face is
+    /// a rectangle that merges as many coplanar unit squares as possible.
+    pub fn mesh(&self) -> Mesh {
+        // For each side of a cube (axis and direction), and each plane along that axis,
+        // the unit squares on the surface, as coordinates along the other two axes.
+        let mut planes = HashMap::<(usize, bool, i32), HashSet<(i32, i32)>>::new();
+        for cube in &self.cubes {
+            for axis in 0..3 {
+                let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+                for positive in [false, true] {
+                    let mut neighbour = *cube;
+                    neighbour.coords[axis] += if positive { 1 } else { -1 };
+                    if self.exterior.contains(&neighbour) {
+                        let plane = cube.coords[axis] + positive as i32;
+                        planes
+                            .entry((axis, positive, plane))
+                            .or_default()
+                            .insert((cube.coords[u], cube.coords[v]));
+                    }
+                }
             }
         }
+        let mut planes = planes.into_iter().collect::<Vec<_>>();
+        planes.sort_unstable_by_key(|&(key, _)| key);
+        let mut mesh = Mesh::default();
+        for ((axis, positive, plane), squares) in planes {
+            for (u0, v0, u1, v1) in merge_squares(squares) {
+                let corner = |u, v| {
+                    let mut corner = Vector3D::zero();
+                    corner.coords[axis] = plane;
+                    corner.coords[(axis + 1) % 3] = u;
+                    corner.coords[(axis + 2) % 3] = v;
+                    corner
+                };
+                // Counter-clockwise when seen from the positive side of the plane
+                let mut corners = [
+                    corner(u0, v0),
+                    corner(u1, v0),
+                    corner(u1, v1),
+                    corner(u0, v1),
+                ];
+                if !positive {
+                    corners.reverse();
+                }
+                let mut normal = Vector3D::zero();
+                normal.coords[axis] = if positive { 1 } else { -1 };
+                mesh.add_face(normal, corners);
+            }
+        }
+        mesh
     }
-    area
+}
+
+/// Greedily merges unit squares into rectangles `(u0, v0, u1, v1)`,
+/// by first growing each rectangle along `u`, and then along `v`.
+fn merge_squares(mut squares: HashSet<(i32, i32)>) -> Vec<(i32, i32, i32, i32)> {
+    let mut sorted = squares.iter().copied().collect::<Vec<_>>();
+    sorted.sort_unstable_by_key(|&(u, v)| (v, u));
+    let mut rectangles = Vec::new();
+    for (u0, v0) in sorted {
+        if !squares.contains(&(u0, v0)) {
+            // Already part of another rectangle
+            continue;
+        }
+        let mut u1 = u0 + 1;
+        while squares.contains(&(u1, v0)) {
+            u1 += 1;
+        }
+        let mut v1 = v0 + 1;
+        while (u0..u1).all(|u| squares.contains(&(u, v1))) {
+            v1 += 1;
+        }
+        for u in u0..u1 {
+            for v in v0..v1 {
+                squares.remove(&(u, v));
+            }
+        }
+        rectangles.push((u0, v0, u1, v1));
+    }
+    rectangles
+}
+
+/// A polygon mesh made of quads, with their corners in counter-clockwise order
+/// when seen from outside.
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    pub vertices: Vec<Vector3D>,
+    /// Indices into `vertices`, with the normal of each face.
+    pub faces: Vec<(Vector3D, [usize; 4])>,
+    indices: HashMap<Vector3D, usize>,
+}
+
+impl Mesh {
+    fn add_face(&mut self, normal: Vector3D, corners: [Vector3D; 4]) {
+        let face = corners.map(|corner| {
+            *self.indices.entry(corner).or_insert_with(|| {
+                self.vertices.push(corner);
+                self.vertices.len() - 1
+            })
+        });
+        self.faces.push((normal, face));
+    }
+
+    /// Formats the mesh as a Wavefront OBJ file.
+    pub fn to_obj(&self) -> String {
+        let mut obj = String::new();
+        for vertex in &self.vertices {
+            let [x, y, z] = vertex.coords;
+            obj += &format!("v {} {} {}\n", x, y, z);
+        }
+        for (_, [a, b, c, d]) in &self.faces {
+            // OBJ indices start at 1
+            obj += &format!("f {} {} {} {}\n", a + 1, b + 1, c + 1, d + 1);
+        }
+        obj
+    }
+
+    /// Formats the mesh as an ASCII STL file, which only supports triangles,
+    /// so every quad is split in two.
+    pub fn to_stl(&self, name: &str) -> String {
+        let mut stl = format!("solid {}\n", name);
+        for (normal, [a, b, c, d]) in &self.faces {
+            for triangle in [[a, b, c], [a, c, d]] {
+                let [x, y, z] = normal.coords;
+                stl += &format!("  facet normal {} {} {}\n", x, y, z);
+                stl += "    outer loop\n";
+                for &index in triangle {
+                    let [x, y, z] = self.vertices[index].coords;
+                    stl += &format!("      vertex {} {} {}\n", x, y, z);
+                }
+                stl += "    endloop\n";
+                stl += "  endfacet\n";
+            }
+        }
+        stl += &format!("endsolid {}\n", name);
+        stl
+    }
+}
+
+#[aoc(day18, part2)]
+pub fn part2(input: &[Vector3D]) -> u64 {
+    Droplet::new(input).exterior_area()
 }
 
 #[cfg(test)]
@@ -92,13 +420,150 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part1(&input), 64);
     }
 
     #[test]
     fn test_part2() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part2(&input), 58);
     }
+
+    #[test]
+    fn test_pockets() {
+        let input = input_generator(&TEST_INPUT).unwrap();
+        let pocket = Vector3D::new(2, 2, 5);
+        assert_eq!(
+            Droplet::new(&input).pockets(),
+            [Pocket {
+                volume: 1,
+                bounds: BoundingBox::from_point(pocket),
+            }]
+        );
+        // A hollow 5x4x4 box, with a wall splitting its 3x2x2 inside in two
+        let mut cubes = BoundingBox::new(Vector3D::zero(), Vector3D::new(4, 3, 3))
+            .into_iter()
+            .filter(|cube| {
+                cube.coords.contains(&0)
+                    || [2, 4].contains(&cube.x())
+                    || cube.coords[1..].contains(&3)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            Droplet::new(&cubes).pockets(),
+            [
+                Pocket {
+                    volume: 4,
+                    bounds: BoundingBox::new(Vector3D::new(1, 1, 1), Vector3D::new(1, 2, 2)),
+                },
+                Pocket {
+                    volume: 4,
+                    bounds: BoundingBox::new(Vector3D::new(3, 1, 1), Vector3D::new(3, 2, 2)),
+                },
+            ]
+        );
+        // A hole in the wall joins them
+        cubes.retain(|&cube| cube != Vector3D::new(2, 2, 2));
+        assert_eq!(
+            Droplet::new(&cubes).pockets(),
+            [Pocket {
+                volume: 9,
+                bounds: BoundingBox::new(Vector3D::new(1, 1, 1), Vector3D::new(3, 2, 2)),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_mesh() {
+        // A 3x1x1 bar has 6 rectangular faces
+        let bar = [
+            Vector3D::new(0, 0, 0),
+            Vector3D::new(1, 0, 0),
+            Vector3D::new(2, 0, 0),
+        ];
+        let mesh = Droplet::new(&bar).mesh();
+        assert_eq!(mesh.vertices.len(), 8);
+        assert_eq!(mesh.faces.len(), 6);
+        let obj = mesh.to_obj();
+        assert_eq!(obj.lines().filter(|line| line.starts_with("v ")).count(), 8);
+        assert_eq!(obj.lines().filter(|line| line.starts_with("f ")).count(), 6);
+        assert!(obj.contains("v 3 1 1\n"));
+        let stl = mesh.to_stl("bar");
+        assert!(stl.starts_with("solid bar\n  facet normal "));
+        assert!(stl.ends_with("  endfacet\nendsolid bar\n"));
+        assert_eq!(stl.matches("facet normal").count(), 12);
+    }
+
+    #[test]
+    fn test_mesh_orientation() {
+        let input = input_generator(&TEST_INPUT).unwrap();
+        let mesh = Droplet::new(&input).mesh();
+        let mut area = 0;
+        for (normal, [a, b, c, _]) in &mesh.faces {
+            let [a, b, c] = [a, b, c].map(|&index| mesh.vertices[index]);
+            let cross = (b - a).cross_product(c - b);
+            // The corners wind counter-clockwise around the outward normal
+            assert_eq!(
+                cross.coords.iter().map(|x| x.abs()).sum::<i32>(),
+                cross.dot(normal)
+            );
+            area += cross.dot(normal);
+        }
+        // The interior pocket is not part of the mesh
+        assert_eq!(area, 58);
+    }
+
+    #[test]
+    fn test_negative_and_distant_coordinates() {
+        let input = input_generator(&TEST_INPUT).unwrap();
+        for offset in [
+            Vector3D::new(-10, -20, -30),
+            Vector3D::new(1_000_000_000, -1_000_000_000, 7),
+        ] {
+            let shifted = input.iter().map(|&cube| cube + offset).collect::<Vec<_>>();
+            assert_eq!(part2(&shifted), 58);
+            let pocket = Vector3D::new(2, 2, 5) + offset;
+            assert_eq!(
+                Droplet::new(&shifted).pockets(),
+                [Pocket {
+                    volume: 1,
+                    bounds: BoundingBox::from_point(pocket),
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn test_sparse_exterior() {
+        let input = input_generator(&TEST_INPUT).unwrap();
+        // The same droplet twice, far apart, is too big for a dense volume
+        let far = Vector3D::new(-2_000_000_000, 0, 2_000_000_000);
+        let mut cubes = input.clone();
+        cubes.extend(input.iter().map(|&cube| cube + far));
+        let droplet = Droplet::new(&cubes);
+        assert!(matches!(droplet.exterior, Exterior::Sparse { .. }));
+        assert_eq!(droplet.exterior_area(), 2 * 58);
+        assert_eq!(droplet.pockets().len(), 2);
+        assert_eq!(
+            droplet.mesh().faces.len(),
+            2 * Droplet::new(&input).mesh().faces.len()
+        );
+        // Both representations agree on everything
+        let dense = Droplet::new(&input);
+        assert!(matches!(dense.exterior, Exterior::Dense { .. }));
+        let cubes = input.iter().cloned().collect::<HashSet<_>>();
+        let sparse = Droplet {
+            exterior: Exterior::sparse(&cubes),
+            cubes,
+        };
+        assert_eq!(sparse.exterior_area(), dense.exterior_area());
+        assert_eq!(sparse.pockets(), dense.pockets());
+        for pos in BoundingBox::new(Vector3D::new(-3, -3, -3), Vector3D::new(6, 6, 9)) {
+            assert_eq!(
+                sparse.exterior.contains(&pos),
+                dense.exterior.contains(&pos)
+            );
+        }
+    }
 }
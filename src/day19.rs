@@ -1,5 +1,10 @@
 use std::str::FromStr;
 
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::util::{lines, Line, ParseError};
+
 #[derive(Debug, Clone)]
 pub struct Ore(u32);
 #[derive(Debug, Clone)]
@@ -17,36 +22,257 @@ pub struct Blueprint {
 }
 
 impl FromStr for Blueprint {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let s = s.strip_prefix("Blueprint ").unwrap();
-        let (number, s) = s.split_once(": Each ore robot costs ").unwrap();
-        let (ore_robot_ore, s) = s.split_once(" ore. Each clay robot costs ").unwrap();
-        let (clay_robot_ore, s) = s.split_once(" ore. Each obsidian robot costs ").unwrap();
-        let (obsidian_robot_ore, s) = s.split_once(" ore and ").unwrap();
-        let (obsidian_robot_clay, s) = s.split_once(" clay. Each geode robot costs ").unwrap();
-        let (geode_robot_ore, s) = s.split_once(" ore and ").unwrap();
-        let geode_robot_obsidian = s.strip_suffix(" obsidian.").unwrap();
+        let line = Line::new(19, 1, s);
+        let s = line.strip_prefix(s, "Blueprint ")?;
+        let (number, s) = line.split_once(s, ": Each ore robot costs ")?;
+        let (ore_robot_ore, s) = line.split_once(s, " ore. Each clay robot costs ")?;
+        let (clay_robot_ore, s) = line.split_once(s, " ore. Each obsidian robot costs ")?;
+        let (obsidian_robot_ore, s) = line.split_once(s, " ore and ")?;
+        let (obsidian_robot_clay, s) = line.split_once(s, " clay. Each geode robot costs ")?;
+        let (geode_robot_ore, s) = line.split_once(s, " ore and ")?;
+        let geode_robot_obsidian = line.strip_suffix(s, " obsidian.")?;
         Ok(Blueprint {
-            number: number.parse().unwrap(),
-            ore_robot: Ore(ore_robot_ore.parse().unwrap()),
-            clay_robot: Ore(clay_robot_ore.parse().unwrap()),
+            number: line.parse(number)?,
+            ore_robot: Ore(line.parse(ore_robot_ore)?),
+            clay_robot: Ore(line.parse(clay_robot_ore)?),
             obsidian_robot: (
-                Ore(obsidian_robot_ore.parse().unwrap()),
-                Clay(obsidian_robot_clay.parse().unwrap()),
+                Ore(line.parse(obsidian_robot_ore)?),
+                Clay(line.parse(obsidian_robot_clay)?),
             ),
             geode_robot: (
-                Ore(geode_robot_ore.parse().unwrap()),
-                Obsidian(geode_robot_obsidian.parse().unwrap()),
+                Ore(line.parse(geode_robot_ore)?),
+                Obsidian(line.parse(geode_robot_obsidian)?),
             ),
         })
     }
 }
 
 #[aoc_generator(day19)]
-pub fn input_generator(input: &str) -> Vec<Blueprint> {
-    input.lines().map(|line| line.parse().unwrap()).collect()
+pub fn input_generator(input: &str) -> Result<Vec<Blueprint>, ParseError> {
+    lines(19, input).map(|line| line.parse_line()).collect()
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Robot {
+    Ore,
+    Clay,
+    Obsidian,
+    Geode,
+}
+
+impl Robot {
+    fn name(self) -> &'static str {
+        match self {
+            Robot::Ore => "ore-collecting",
+            Robot::Clay => "clay-collecting",
+            Robot::Obsidian => "obsidian-collecting",
+            Robot::Geode => "geode-cracking",
+        }
+    }
+}
+
+impl Blueprint {
+    /// The ore, clay and obsidian needed to build a robot.
+    fn cost(&self, robot: Robot) -> (u32, u32, u32) {
+        match robot {
+            Robot::Ore => (self.ore_robot.0, 0, 0),
+            Robot::Clay => (self.clay_robot.0, 0, 0),
+            Robot::Obsidian => (self.obsidian_robot.0 .0, self.obsidian_robot.1 .0, 0),
+            Robot::Geode => (self.geode_robot.0 .0, 0, self.geode_robot.1 .0),
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct Inventory {
+    pub ore: u32,
+    pub clay: u32,
+    pub obsidian: u32,
+    pub geodes: u32,
+    pub ore_robots: u32,
+    pub clay_robots: u32,
+    pub obsidian_robots: u32,
+    pub geode_robots: u32,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Build {
+    pub robot: Robot,
+    /// The (1-based) minute during which the robot is built.
+    pub minute: u32,
+    /// The inventory at the end of that minute, including the new robot.
+    pub inventory: Inventory,
+}
+
+/// The best way to use a blueprint.
+#[derive(Debug, Clone)]
+pub struct Solution {
+    pub blueprint: Blueprint,
+    pub time: u32,
+    pub geodes: u32,
+    pub builds: Vec<Build>,
+}
+
+impl Solution {
+    /// Replays the builds, and calls `log` with each line of the transcript.
+    fn simulate(
+        blueprint: &Blueprint,
+        time: u32,
+        plan: &[(Robot, u32)],
+        mut log: impl FnMut(String),
+    ) -> Vec<Build> {
+        let mut inventory = Inventory {
+            ore_robots: 1,
+            ..Inventory::default()
+        };
+        let mut plan = plan.iter().peekable();
+        let mut builds = Vec::new();
+        for minute in 1..=time {
+            if minute > 1 {
+                log(String::new());
+            }
+            log(format!("== Minute {} ==", minute));
+            let robot = plan
+                .next_if(|&&(_, m)| m == minute)
+                .map(|&(robot, _)| robot);
+            if let Some(robot) = robot {
+                let (ore, clay, obsidian) = blueprint.cost(robot);
+                let spent = [(ore, "ore"), (clay, "clay"), (obsidian, "obsidian")]
+                    .iter()
+                    .filter(|(amount, _)| *amount > 0)
+                    .map(|(amount, resource)| format!("{} {}", amount, resource))
+                    .collect::<Vec<_>>();
+                let article = if robot == Robot::Ore || robot == Robot::Obsidian {
+                    "an"
+                } else {
+                    "a"
+                };
+                log(format!(
+                    "Spend {} to start building {} {} robot.",
+                    spent.join(" and "),
+                    article,
+                    robot.name()
+                ));
+                inventory.ore -= ore;
+                inventory.clay -= clay;
+                inventory.obsidian -= obsidian;
+            }
+            let collectors = [
+                (inventory.ore_robots, Robot::Ore),
+                (inventory.clay_robots, Robot::Clay),
+                (inventory.obsidian_robots, Robot::Obsidian),
+                (inventory.geode_robots, Robot::Geode),
+            ];
+            inventory.ore += inventory.ore_robots;
+            inventory.clay += inventory.clay_robots;
+            inventory.obsidian += inventory.obsidian_robots;
+            inventory.geodes += inventory.geode_robots;
+            for (count, robot) in collectors {
+                if count == 0 {
+                    continue;
+                }
+                let (s, verb) = if count == 1 { ("", "s") } else { ("s", "") };
+                let line = match robot {
+                    Robot::Geode => format!(
+                        "{} geode-cracking robot{} crack{} {} geode{}; you now have {} open geode{}.",
+                        count,
+                        s,
+                        verb,
+                        count,
+                        s,
+                        inventory.geodes,
+                        if inventory.geodes == 1 { "" } else { "s" }
+                    ),
+                    _ => {
+                        let (resource, total) = match robot {
+                            Robot::Ore => ("ore", inventory.ore),
+                            Robot::Clay => ("clay", inventory.clay),
+                            _ => ("obsidian", inventory.obsidian),
+                        };
+                        format!(
+                            "{} {} robot{} collect{} {} {}; you now have {} {}.",
+                            count,
+                            robot.name(),
+                            s,
+                            verb,
+                            count,
+                            resource,
+                            total,
+                            resource
+                        )
+                    }
+                };
+                log(line);
+            }
+            if let Some(robot) = robot {
+                let robots = match robot {
+                    Robot::Ore => &mut inventory.ore_robots,
+                    Robot::Clay => &mut inventory.clay_robots,
+                    Robot::Obsidian => &mut inventory.obsidian_robots,
+                    Robot::Geode => &mut inventory.geode_robots,
+                };
+                *robots += 1;
+                log(format!(
+                    "The new {} robot is ready; you now have {} of them.",
+                    robot.name(),
+                    robots
+                ));
+                builds.push(Build {
+                    robot,
+                    minute,
+                    inventory,
+                });
+            }
+        }
+        builds
+    }
+
+    /// Describes every minute, like the puzzle does.
+    pub fn transcript(&self) -> String {
+        let plan = self
+            .builds
+            .iter()
+            .map(|build| (build.robot, build.minute))
+            .collect::<Vec<_>>();
+        let mut transcript = String::new();
+        Solution::simulate(&self.blueprint, self.time, &plan, |line| {
+            transcript += &line;
+            transcript.push('\n');
+        });
+        transcript
+    }
+}
+
+/// Finds the most geodes that can be opened with a blueprint, and how.
+pub fn optimize(blueprint: &Blueprint, time: u32) -> Solution {
+    let mut search = Search {
+        blueprint,
+        total_time: time,
+        most_geodes: 0,
+        plan: Vec::new(),
+        best_plan: Vec::new(),
+    };
+    State::new().solve(time, &mut search);
+    let builds = Solution::simulate(blueprint, time, &search.best_plan, |_| {});
+    Solution {
+        blueprint: blueprint.clone(),
+        time,
+        geodes: search.most_geodes,
+        builds,
+    }
+}
+
+struct Search<'a> {
+    blueprint: &'a Blueprint,
+    total_time: u32,
+    most_geodes: u32,
+    /// The robots built so far, with the minute they're built.
+    plan: Vec<(Robot, u32)>,
+    /// The plan that opens `most_geodes` geodes.
+    best_plan: Vec<(Robot, u32)>,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -72,25 +298,45 @@ impl State {
         }
     }
 
-    fn most_geodes(&mut self, time: u32, blueprint: &Blueprint) -> u32 {
-        let mut most_geodes = 0;
-        self.solve(time, blueprint, &mut most_geodes);
-        most_geodes
+    /// Waits until a robot can be built, then builds it and continues from there.
+    fn build(&self, robot: Robot, time: u32, time_to_robot: u32, search: &mut Search) {
+        let (ore, clay, obsidian) = search.blueprint.cost(robot);
+        let mut next = self.clone();
+        let time = time - time_to_robot;
+        next.advance(time_to_robot);
+        next.ore -= ore;
+        next.clay -= clay;
+        next.obsidian -= obsidian;
+        match robot {
+            Robot::Ore => next.ore_robots += 1,
+            Robot::Clay => next.clay_robots += 1,
+            Robot::Obsidian => next.obsidian_robots += 1,
+            // Optimization: instead of tracking the number of geode-cracking robots,
+            // compute how many geodes the new robot will crack ahead of time.
+            Robot::Geode => next.geode += time,
+        }
+        search.plan.push((robot, search.total_time - time));
+        next.solve(time, search);
+        search.plan.pop();
     }
 
-    fn solve(&mut self, time: u32, blueprint: &Blueprint, most_geodes: &mut u32) {
+    fn solve(&mut self, time: u32, search: &mut Search) {
         if time == 0 {
             return;
         }
+        let blueprint = search.blueprint;
         // The theoretical maximum number of geodes we could make in the remaining time
         // if we had infinite resources. That is: if we could create a new geode-cracking robot
         // in every time step from now until the end.
         // If `most_geodes` is already better than this maximum, then this state is useless.
         let max_geodes = self.geode + (time - 1) * time;
-        if max_geodes <= *most_geodes {
+        if max_geodes <= search.most_geodes {
             return;
         }
-        *most_geodes = (*most_geodes).max(self.geode);
+        if self.geode > search.most_geodes {
+            search.most_geodes = self.geode;
+            search.best_plan = search.plan.clone();
+        }
         // Try to build a geode-cracking robot.
         if self.obsidian_robots > 0 {
             let needed_ore = blueprint.geode_robot.0 .0.saturating_sub(self.ore);
@@ -101,19 +347,11 @@ impl State {
             // Need 1 more time unit to make the geode-cracking robot itself
             let time_to_robot = time_to_ore.max(time_to_obsidian) + 1;
             if time > time_to_robot {
-                let mut next = self.clone();
-                let time = time - time_to_robot;
-                next.advance(time_to_robot);
-                next.ore -= blueprint.geode_robot.0 .0;
-                next.obsidian -= blueprint.geode_robot.1 .0;
-                // Optimization: instead of tracking the number of geode-cracking robots,
-                // compute how many geodes the new robot will crack ahead of time.
-                next.geode += time;
-                next.solve(time, blueprint, most_geodes);
+                self.build(Robot::Geode, time, time_to_robot, search);
             }
         }
         // Check again, in case we already found a much better solution in the previous step.
-        if max_geodes <= *most_geodes {
+        if max_geodes <= search.most_geodes {
             return;
         }
         // Try to build an obsidian-collecting robot *only* if we could still theoretically
@@ -127,16 +365,10 @@ impl State {
             let time_to_clay = div_round_up(needed_clay, self.clay_robots);
             let time_to_robot = time_to_ore.max(time_to_clay) + 1;
             if time > time_to_robot {
-                let mut next = self.clone();
-                let time = time - time_to_robot;
-                next.advance(time_to_robot);
-                next.ore -= blueprint.obsidian_robot.0 .0;
-                next.clay -= blueprint.obsidian_robot.1 .0;
-                next.obsidian_robots += 1;
-                next.solve(time, blueprint, most_geodes);
+                self.build(Robot::Obsidian, time, time_to_robot, search);
             }
         }
-        if max_geodes <= *most_geodes {
+        if max_geodes <= search.most_geodes {
             return;
         }
         // Try to build a clay-collecting robot *only* if we could still theoretically
@@ -146,15 +378,10 @@ impl State {
             let time_to_ore = div_round_up(needed_ore, self.ore_robots);
             let time_to_robot = time_to_ore + 1;
             if time > time_to_robot {
-                let mut next = self.clone();
-                let time = time - time_to_robot;
-                next.advance(time_to_robot);
-                next.ore -= blueprint.clay_robot.0;
-                next.clay_robots += 1;
-                next.solve(time, blueprint, most_geodes);
+                self.build(Robot::Clay, time, time_to_robot, search);
             }
         }
-        if max_geodes <= *most_geodes {
+        if max_geodes <= search.most_geodes {
             return;
         }
         // Try to build an ore-collecting robot *only* if we could still theoretically
@@ -169,12 +396,7 @@ impl State {
             let time_to_ore = div_round_up(needed_ore, self.ore_robots);
             let time_to_robot = time_to_ore + 1;
             if time > time_to_robot {
-                let mut next = self.clone();
-                let time = time - time_to_robot;
-                next.advance(time_to_robot);
-                next.ore -= blueprint.ore_robot.0;
-                next.ore_robots += 1;
-                next.solve(time, blueprint, most_geodes);
+                self.build(Robot::Ore, time, time_to_robot, search);
             }
         }
     }
@@ -188,18 +410,24 @@ impl State {
 
 #[aoc(day19, part1)]
 pub fn part1(input: &[Blueprint]) -> u32 {
-    input
-        .iter()
-        .map(|blueprint| (blueprint.number as u32) * State::new().most_geodes(24, blueprint))
+    #[cfg(not(feature = "parallel"))]
+    let blueprints = input.iter();
+    #[cfg(feature = "parallel")]
+    let blueprints = input.par_iter();
+    blueprints
+        .map(|blueprint| (blueprint.number as u32) * optimize(blueprint, 24).geodes)
         .sum()
 }
 
 #[aoc(day19, part2)]
 pub fn part2(input: &[Blueprint]) -> u32 {
-    input
-        .iter()
-        .take(3)
-        .map(|blueprint| State::new().most_geodes(32, blueprint))
+    let blueprints = &input[..input.len().min(3)];
+    #[cfg(not(feature = "parallel"))]
+    let blueprints = blueprints.iter();
+    #[cfg(feature = "parallel")]
+    let blueprints = blueprints.par_iter();
+    blueprints
+        .map(|blueprint| optimize(blueprint, 32).geodes)
         .product()
 }
 
@@ -213,13 +441,88 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part1(&input), 33);
     }
 
     #[test]
     fn test_part2() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part2(&input), 56 * 62);
     }
+
+    #[test]
+    fn test_build_schedule() {
+        let input = input_generator(&TEST_INPUT).unwrap();
+        let solution = optimize(&input[0], 24);
+        assert_eq!(solution.geodes, 9);
+        let schedule = solution
+            .builds
+            .iter()
+            .map(|build| (build.robot, build.minute))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            schedule,
+            [
+                (Robot::Clay, 3),
+                (Robot::Clay, 5),
+                (Robot::Clay, 7),
+                (Robot::Obsidian, 11),
+                (Robot::Clay, 12),
+                (Robot::Obsidian, 15),
+                (Robot::Geode, 18),
+                (Robot::Geode, 21),
+            ]
+        );
+        assert_eq!(
+            solution.builds[3].inventory,
+            Inventory {
+                ore: 2,
+                clay: 4,
+                obsidian: 0,
+                geodes: 0,
+                ore_robots: 1,
+                clay_robots: 3,
+                obsidian_robots: 1,
+                geode_robots: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_schedule_replays_to_most_geodes() {
+        let input = input_generator(&TEST_INPUT).unwrap();
+        for (blueprint, time) in [(&input[0], 32), (&input[1], 24), (&input[1], 32)] {
+            let solution = optimize(blueprint, time);
+            let last = solution.builds.last().unwrap();
+            let geodes = last.inventory.geodes + last.inventory.geode_robots * (time - last.minute);
+            assert_eq!(geodes, solution.geodes);
+        }
+    }
+
+    #[test]
+    fn test_transcript() {
+        let input = input_generator(&TEST_INPUT).unwrap();
+        let transcript = optimize(&input[0], 24).transcript();
+        assert!(transcript.starts_with(
+            "== Minute 1 ==\n\
+             1 ore-collecting robot collects 1 ore; you now have 1 ore.\n\
+             \n\
+             == Minute 2 ==\n"
+        ));
+        assert!(transcript.contains(
+            "== Minute 11 ==\n\
+             Spend 3 ore and 14 clay to start building an obsidian-collecting robot.\n\
+             1 ore-collecting robot collects 1 ore; you now have 2 ore.\n\
+             3 clay-collecting robots collect 3 clay; you now have 4 clay.\n\
+             The new obsidian-collecting robot is ready; you now have 1 of them.\n"
+        ));
+        assert!(transcript.ends_with(
+            "== Minute 24 ==\n\
+             1 ore-collecting robot collects 1 ore; you now have 6 ore.\n\
+             4 clay-collecting robots collect 4 clay; you now have 41 clay.\n\
+             2 obsidian-collecting robots collect 2 obsidian; you now have 8 obsidian.\n\
+             2 geode-cracking robots crack 2 geodes; you now have 9 open geodes.\n"
+        ));
+    }
 }
@@ -1,3 +1,5 @@
+use crate::util::{lines, ParseError};
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Hand {
     Rock,
@@ -19,24 +21,23 @@ pub struct Input {
 }
 
 #[aoc_generator(day2)]
-pub fn input_generator(input: &str) -> Vec<Input> {
-    input
-        .lines()
+pub fn input_generator(input: &str) -> Result<Vec<Input>, ParseError> {
+    lines(2, input)
         .map(|line| {
-            let (enemy, you) = line.split_once(' ').unwrap();
+            let (enemy, you) = line.split_once(line.text, " ")?;
             let enemy = match enemy {
                 "A" => Hand::Rock,
                 "B" => Hand::Paper,
                 "C" => Hand::Scissors,
-                _ => panic!("invalid input"),
+                _ => return Err(line.error(enemy, "invalid hand")),
             };
             let you = match you {
                 "X" => Strategy::X,
                 "Y" => Strategy::Y,
                 "Z" => Strategy::Z,
-                _ => panic!("invalid input"),
+                _ => return Err(line.error(you, "invalid strategy")),
             };
-            Input { you, enemy }
+            Ok(Input { you, enemy })
         })
         .collect()
 }
@@ -155,13 +156,13 @@ C Z
 
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part1(&input), 15);
     }
 
     #[test]
     fn test_part2() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part2(&input), 12);
     }
 }
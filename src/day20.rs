@@ -1,9 +1,36 @@
+use crate::util::{lines, IndexedList, ParseError};
+
 #[aoc_generator(day20)]
-pub fn input_generator(input: &str) -> Vec<i64> {
-    input.lines().map(|line| line.parse().unwrap()).collect()
+pub fn input_generator(input: &str) -> Result<Vec<i64>, ParseError> {
+    lines(20, input).map(|line| line.parse(line.text)).collect()
+}
+
+/// Moves every value by its shift, in the original order.
+pub fn mix(values: &mut [i64], order: &[i64], rounds: usize) {
+    let mut indices = (0..values.len()).collect::<IndexedList<_>>();
+    for _ in 0..rounds {
+        for (i, &shift) in order.iter().enumerate() {
+            if shift == 0 {
+                continue;
+            }
+            // The handle of each element is its original index.
+            let idx = indices.index_of(i);
+            let len = indices.len() as i64 - 1;
+            let mut new_idx = (((idx as i64 + shift) % len) + len) % len;
+            if new_idx == 0 {
+                new_idx = len;
+            }
+            indices.move_to(i, new_idx as usize);
+        }
+    }
+    let orig_values = values.to_vec();
+    for (i, &idx) in indices.iter().enumerate() {
+        values[i] = orig_values[idx];
+    }
 }
 
-fn mix(values: &mut Vec<i64>, order: &[i64], rounds: usize) {
+/// Same as `mix`, but on a plain `Vec`. Quadratic, only kept for comparison.
+pub fn mix_naive(values: &mut [i64], order: &[i64], rounds: usize) {
     let mut indices = (0..values.len()).collect::<Vec<_>>();
     for _ in 0..rounds {
         for (i, &shift) in order.iter().enumerate() {
@@ -20,7 +47,7 @@ fn mix(values: &mut Vec<i64>, order: &[i64], rounds: usize) {
             indices.insert(new_idx as usize, value);
         }
     }
-    let orig_values = values.clone();
+    let orig_values = values.to_vec();
     for (i, idx) in indices.into_iter().enumerate() {
         values[i] = orig_values[idx];
     }
@@ -66,21 +93,29 @@ mod tests {
 
     #[test]
     fn test_mix() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         let mut values = input.clone();
         mix(&mut values, &input, 1);
         assert_eq!(&values, &[1, 2, -3, 4, 0, 3, -2]);
     }
 
+    #[test]
+    fn test_mix_naive() {
+        let input = input_generator(&TEST_INPUT).unwrap();
+        let mut values = input.clone();
+        mix_naive(&mut values, &input, 1);
+        assert_eq!(&values, &[1, 2, -3, 4, 0, 3, -2]);
+    }
+
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part1(&input), 3);
     }
 
     #[test]
     fn test_mix_10() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         let mut values = input.iter().map(|x| x * 811589153).collect::<Vec<_>>();
         let order = values.clone();
         mix(&mut values, &order, 10);
@@ -100,7 +135,7 @@ mod tests {
 
     #[test]
     fn test_part2() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part2(&input), 1623178306);
     }
 }
@@ -1,4 +1,8 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::util::{lines, ParseError, Rational};
 
 #[derive(Debug, Clone)]
 pub enum Yell {
@@ -9,138 +13,656 @@ pub enum Yell {
     Divide(String, String),
 }
 
+impl Yell {
+    /// The monkeys this monkey waits for, if it yells an operation.
+    pub fn operands(&self) -> Option<(&str, &str)> {
+        match self {
+            Yell::Number(_) => None,
+            Yell::Add(left, right)
+            | Yell::Subtract(left, right)
+            | Yell::Multiply(left, right)
+            | Yell::Divide(left, right) => Some((left, right)),
+        }
+    }
+}
+
+impl Display for Yell {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Yell::Number(x) => write!(f, "{}", x),
+            Yell::Add(left, right) => write!(f, "{} + {}", left, right),
+            Yell::Subtract(left, right) => write!(f, "{} - {}", left, right),
+            Yell::Multiply(left, right) => write!(f, "{} * {}", left, right),
+            Yell::Divide(left, right) => write!(f, "{} / {}", left, right),
+        }
+    }
+}
+
 pub type Input = HashMap<String, Yell>;
 
 #[aoc_generator(day21)]
-pub fn input_generator(input: &str) -> Input {
-    input
-        .lines()
-        .map(|line| {
-            let (name, yell) = line.split_once(": ").unwrap();
-            let yell = if let Some((left, right)) = yell.split_once(" + ") {
-                Yell::Add(left.to_string(), right.to_string())
-            } else if let Some((left, right)) = yell.split_once(" - ") {
-                Yell::Subtract(left.to_string(), right.to_string())
-            } else if let Some((left, right)) = yell.split_once(" * ") {
-                Yell::Multiply(left.to_string(), right.to_string())
-            } else if let Some((left, right)) = yell.split_once(" / ") {
-                Yell::Divide(left.to_string(), right.to_string())
-            } else {
-                Yell::Number(yell.parse().unwrap())
-            };
-            (name.to_string(), yell)
-        })
-        .collect()
+pub fn input_generator(input: &str) -> Result<Input, ParseError> {
+    let mut yells = Input::new();
+    let mut lines_by_name = HashMap::new();
+    for line in lines(21, input) {
+        let (name, yell) = line.split_once(line.text, ": ")?;
+        let yell = if let Some((left, right)) = yell.split_once(" + ") {
+            Yell::Add(left.to_string(), right.to_string())
+        } else if let Some((left, right)) = yell.split_once(" - ") {
+            Yell::Subtract(left.to_string(), right.to_string())
+        } else if let Some((left, right)) = yell.split_once(" * ") {
+            Yell::Multiply(left.to_string(), right.to_string())
+        } else if let Some((left, right)) = yell.split_once(" / ") {
+            Yell::Divide(left.to_string(), right.to_string())
+        } else {
+            Yell::Number(line.parse(yell)?)
+        };
+        if lines_by_name.insert(name, line).is_some() {
+            return Err(line.error(name, format!("duplicate monkey `{}`", name)));
+        }
+        yells.insert(name.to_string(), yell);
+    }
+    validate(&yells).map_err(|err| match &err {
+        ValidationError::UndefinedMonkey { monkey, missing } => {
+            let line = lines_by_name[monkey.as_str()];
+            let (_, yell) = line.text.split_once(": ").unwrap();
+            let at = yell.split(' ').find(|operand| operand == missing).unwrap();
+            line.error(at, err.to_string())
+        }
+        ValidationError::Cycle(chain) => {
+            let line = lines_by_name[chain[0].as_str()];
+            line.error(line.text, err.to_string())
+        }
+    })?;
+    Ok(yells)
 }
 
-fn solve(name: &str, yells: &Input, cache: &mut HashMap<String, i64>) -> i64 {
-    if let Some(&value) = cache.get(name) {
-        return value;
-    }
-    let value = match yells.get(name).unwrap() {
-        Yell::Number(x) => *x,
-        Yell::Add(left, right) => solve(left, yells, cache) + solve(right, yells, cache),
-        Yell::Subtract(left, right) => solve(left, yells, cache) - solve(right, yells, cache),
-        Yell::Multiply(left, right) => solve(left, yells, cache) * solve(right, yells, cache),
-        Yell::Divide(left, right) => solve(left, yells, cache) / solve(right, yells, cache),
-    };
-    cache.insert(name.to_string(), value);
-    value
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ValidationError {
+    /// `monkey` waits for `missing`, which doesn't exist.
+    UndefinedMonkey { monkey: String, missing: String },
+    /// Monkeys that wait for each other, starting and ending with the same monkey.
+    Cycle(Vec<String>),
 }
 
-#[aoc(day21, part1)]
-pub fn part1(input: &Input) -> i64 {
-    solve("root", input, &mut HashMap::new())
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationError::UndefinedMonkey { monkey, missing } => {
+                write!(f, "`{}` waits for undefined monkey `{}`", monkey, missing)
+            }
+            ValidationError::Cycle(chain) => {
+                write!(f, "dependency cycle: {}", chain.join(" -> "))
+            }
+        }
+    }
 }
 
-fn depends_on_humn<'a>(name: &'a str, yells: &'a Input) -> bool {
-    if name == "humn" {
-        return true;
+impl Error for ValidationError {}
+
+/// Checks that every monkey only waits for monkeys that exist, and that no monkeys wait for each other.
+pub fn validate(yells: &Input) -> Result<(), ValidationError> {
+    #[derive(Copy, Clone, Eq, PartialEq)]
+    enum Visit {
+        InProgress,
+        Done,
     }
-    match yells.get(name).unwrap() {
-        Yell::Number(_x) => false,
-        Yell::Add(left, right)
-        | Yell::Subtract(left, right)
-        | Yell::Multiply(left, right)
-        | Yell::Divide(left, right) => {
-            depends_on_humn(left, yells) || depends_on_humn(right, yells)
+
+    // The operands still to visit, in reverse order so the left one is visited first.
+    let pending = |name: &str| {
+        yells[name]
+            .operands()
+            .map_or(Vec::new(), |(left, right)| vec![right, left])
+    };
+    let mut visits = HashMap::new();
+    let mut names = yells.keys().map(String::as_str).collect::<Vec<_>>();
+    // Visit in a fixed order, so errors are reproducible.
+    names.sort_unstable();
+    for start in names {
+        if visits.contains_key(start) {
+            continue;
+        }
+        // A depth-first search without recursion, since the input might be deep.
+        visits.insert(start, Visit::InProgress);
+        let mut path = vec![(start, pending(start))];
+        while let Some((name, operands)) = path.last_mut() {
+            let name = *name;
+            let Some(operand) = operands.pop() else {
+                visits.insert(name, Visit::Done);
+                path.pop();
+                continue;
+            };
+            match visits.get(operand) {
+                Some(Visit::Done) => {}
+                Some(Visit::InProgress) => {
+                    let cycle_start = path.iter().position(|&(n, _)| n == operand).unwrap();
+                    let mut chain = path[cycle_start..]
+                        .iter()
+                        .map(|(n, _)| n.to_string())
+                        .collect::<Vec<_>>();
+                    chain.push(operand.to_string());
+                    return Err(ValidationError::Cycle(chain));
+                }
+                None if !yells.contains_key(operand) => {
+                    return Err(ValidationError::UndefinedMonkey {
+                        monkey: name.to_string(),
+                        missing: operand.to_string(),
+                    });
+                }
+                None => {
+                    visits.insert(operand, Visit::InProgress);
+                    path.push((operand, pending(operand)));
+                }
+            }
         }
     }
+    Ok(())
 }
 
-fn solve_for_humn(name: &str, value: i64, yells: &Input, cache: &mut HashMap<String, i64>) -> i64 {
-    if name == "humn" {
-        return value;
+/// Exports the graph of which monkeys wait for which, in Graphviz DOT format.
+/// Every monkey and edge on a path from `from` to `to` is highlighted.
+pub fn to_dot(yells: &Input, from: &str, to: &str) -> String {
+    // Find the monkeys that `to` (transitively) waits for, and which of them depend on `from`.
+    // If `to` waits for an undefined monkey, nothing is highlighted.
+    let mut path = HashMap::new();
+    for name in dependency_order(yells, to, Some(from)).unwrap_or_default() {
+        let on_path = name == from
+            || yells[name]
+                .operands()
+                .map_or(false, |(left, right)| path[left] || path[right]);
+        path.insert(name, on_path);
+    }
+    let is_highlighted = |name: &str| path.get(name) == Some(&true);
+    const HIGHLIGHT: &str = "color=red, penwidth=2";
+
+    let mut names = yells.keys().collect::<Vec<_>>();
+    names.sort_unstable();
+    let mut dot = String::from("digraph monkeys {\n    rankdir=BT;\n");
+    for &name in &names {
+        let label = match &yells[name] {
+            Yell::Number(x) => format!("{}\\n{}", name, x),
+            yell => format!("{}\\n{}", name, yell),
+        };
+        let style = if is_highlighted(name) {
+            format!(", {}", HIGHLIGHT)
+        } else {
+            String::new()
+        };
+        dot += &format!("    \"{}\" [label=\"{}\"{}];\n", name, label, style);
     }
-    match yells.get(name).unwrap() {
-        Yell::Number(_) => panic!("unexpected number"),
-        Yell::Add(left, right) => {
-            if depends_on_humn(left, yells) {
-                // X = L + R => L = X - R
-                let left_value = value - solve(right, yells, cache);
-                solve_for_humn(left, left_value, yells, cache)
+    for &name in &names {
+        let Some((left, right)) = yells[name].operands() else {
+            continue;
+        };
+        for operand in [left, right] {
+            let style = if is_highlighted(name) && is_highlighted(operand) {
+                format!(" [{}]", HIGHLIGHT)
             } else {
-                // X = L + R => R = X - L
-                let right_value = value - solve(left, yells, cache);
-                solve_for_humn(right, right_value, yells, cache)
+                String::new()
+            };
+            dot += &format!("    \"{}\" -> \"{}\"{};\n", operand, name, style);
+        }
+    }
+    dot += "}\n";
+    dot
+}
+
+/// Lists `root` and every monkey it (transitively) waits for, each after the monkeys it waits for,
+/// without looking past `variable`.
+///
+/// Assumes no monkeys wait for each other, which `validate()` checks.
+fn dependency_order<'a>(
+    yells: &'a Input,
+    root: &'a str,
+    variable: Option<&str>,
+) -> Result<Vec<&'a str>, SolveError> {
+    let mut order = Vec::new();
+    let mut visited = HashSet::new();
+    // A depth-first search without recursion, since the input might be deep.
+    // Each monkey is pushed again once its operands are on the stack, to list it after them.
+    let mut stack = vec![(root, false)];
+    while let Some((name, operands_pushed)) = stack.pop() {
+        if operands_pushed {
+            order.push(name);
+            continue;
+        }
+        if !visited.insert(name) {
+            continue;
+        }
+        stack.push((name, true));
+        if Some(name) == variable {
+            continue;
+        }
+        let yell = yells
+            .get(name)
+            .ok_or_else(|| SolveError::UnknownMonkey(name.to_string()))?;
+        if let Some((left, right)) = yell.operands() {
+            stack.push((right, false));
+            stack.push((left, false));
+        }
+    }
+    Ok(order)
+}
+
+fn solve(root: &str, yells: &Input) -> Result<i64, SolveError> {
+    let mut values = HashMap::<&str, i64>::new();
+    for name in dependency_order(yells, root, None)? {
+        let operands = |left: &String, right: &String| {
+            let value = |operand: &String| values[operand.as_str()];
+            (value(left), value(right))
+        };
+        let value = match &yells[name] {
+            Yell::Number(x) => Some(*x),
+            Yell::Add(left, right) => {
+                let (left, right) = operands(left, right);
+                left.checked_add(right)
+            }
+            Yell::Subtract(left, right) => {
+                let (left, right) = operands(left, right);
+                left.checked_sub(right)
+            }
+            Yell::Multiply(left, right) => {
+                let (left, right) = operands(left, right);
+                left.checked_mul(right)
+            }
+            Yell::Divide(left, right) => {
+                let (left, right) = operands(left, right);
+                if right == 0 {
+                    return Err(SolveError::DivisionByZero);
+                }
+                left.checked_div(right)
             }
         }
-        Yell::Subtract(left, right) => {
-            if depends_on_humn(left, yells) {
-                // X = L - R => L = X + R
-                let left_value = value + solve(right, yells, cache);
-                solve_for_humn(left, left_value, yells, cache)
-            } else {
-                // X = L - R => R = L - X
-                let right_value = solve(left, yells, cache) - value;
-                solve_for_humn(right, right_value, yells, cache)
+        .ok_or(SolveError::Overflow)?;
+        values.insert(name, value);
+    }
+    Ok(values[root])
+}
+
+#[aoc(day21, part1)]
+pub fn part1(input: &Input) -> Result<i64, SolveError> {
+    solve("root", input)
+}
+
+/// A monkey's yell as an expression, in terms of a single unknown variable.
+///
+/// Nodes only refer to the nodes before them, and the last node is the whole expression.
+/// So a monkey that several others wait for is a single shared node, and the nodes can be
+/// computed front to back, however deeply they are nested.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Expression {
+    nodes: Vec<Node>,
+}
+
+/// A node of an `Expression`, with its operands as indices of earlier nodes.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Node {
+    Number(i64),
+    Variable,
+    Add(usize, usize),
+    Subtract(usize, usize),
+    Multiply(usize, usize),
+    Divide(usize, usize),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SolveError {
+    UnknownMonkey(String),
+    /// The monkey doesn't yell an operation, so it can't be turned into an equation.
+    NotAnEquation(String),
+    DivisionByZero,
+    /// The equation has a higher degree than we can solve.
+    NonLinear(usize),
+    NoSolution,
+    InfinitelyManySolutions,
+    /// The only solution is a fraction, which no monkey can yell.
+    NotAnInteger(Rational),
+    /// A number got too big to compute exactly.
+    Overflow,
+}
+
+impl Display for SolveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolveError::UnknownMonkey(name) => write!(f, "unknown monkey `{}`", name),
+            SolveError::NotAnEquation(name) => write!(f, "`{}` doesn't yell an operation", name),
+            SolveError::DivisionByZero => write!(f, "division by zero"),
+            SolveError::NonLinear(degree) => {
+                write!(f, "cannot solve an equation of degree {}", degree)
+            }
+            SolveError::NoSolution => write!(f, "no solution"),
+            SolveError::InfinitelyManySolutions => write!(f, "infinitely many solutions"),
+            SolveError::NotAnInteger(solution) => {
+                write!(f, "the solution {} is not an integer", solution)
             }
+            SolveError::Overflow => write!(f, "a number is too big to compute exactly"),
         }
-        Yell::Multiply(left, right) => {
-            if depends_on_humn(left, yells) {
-                // X = L * R => L = X / R
-                let left_value = value / solve(right, yells, cache);
-                solve_for_humn(left, left_value, yells, cache)
-            } else {
-                // X = L * R => R = X / L
-                let right_value = value / solve(left, yells, cache);
-                solve_for_humn(right, right_value, yells, cache)
+    }
+}
+
+impl Error for SolveError {}
+
+/// Builds the expression for what a monkey yells, treating `variable` as unknown.
+pub fn expression(yells: &Input, name: &str, variable: &str) -> Result<Expression, SolveError> {
+    let order = dependency_order(yells, name, Some(variable))?;
+    let index = order
+        .iter()
+        .enumerate()
+        .map(|(i, &name)| (name, i))
+        .collect::<HashMap<_, _>>();
+    let operands = |left: &String, right: &String| (index[left.as_str()], index[right.as_str()]);
+    let nodes = order
+        .iter()
+        .map(|&name| {
+            if name == variable {
+                return Node::Variable;
+            }
+            match &yells[name] {
+                &Yell::Number(x) => Node::Number(x),
+                Yell::Add(left, right) => {
+                    let (left, right) = operands(left, right);
+                    Node::Add(left, right)
+                }
+                Yell::Subtract(left, right) => {
+                    let (left, right) = operands(left, right);
+                    Node::Subtract(left, right)
+                }
+                Yell::Multiply(left, right) => {
+                    let (left, right) = operands(left, right);
+                    Node::Multiply(left, right)
+                }
+                Yell::Divide(left, right) => {
+                    let (left, right) = operands(left, right);
+                    Node::Divide(left, right)
+                }
             }
+        })
+        .collect();
+    Ok(Expression { nodes })
+}
+
+/// A polynomial with exact coefficients, lowest degree first and without trailing zeros.
+///
+/// All arithmetic is checked, and returns `None` if a coefficient overflows.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Polynomial {
+    coefficients: Vec<Rational>,
+}
+
+impl Polynomial {
+    fn new(mut coefficients: Vec<Rational>) -> Self {
+        while coefficients.last().map_or(false, |c| c.is_zero()) {
+            coefficients.pop();
         }
-        Yell::Divide(left, right) => {
-            if depends_on_humn(left, yells) {
-                // X = L / R => L = X * R
-                let left_value = value * solve(right, yells, cache);
-                solve_for_humn(left, left_value, yells, cache)
-            } else {
-                // X = L / R => R = L / X
-                let right_value = solve(left, yells, cache) / value;
-                solve_for_humn(right, right_value, yells, cache)
+        Self { coefficients }
+    }
+
+    pub fn constant(value: Rational) -> Self {
+        Self::new(vec![value])
+    }
+
+    pub fn variable() -> Self {
+        Self::new(vec![Rational::ZERO, Rational::ONE])
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coefficients.is_empty()
+    }
+
+    /// The degree of the polynomial, or `None` for the zero polynomial.
+    pub fn degree(&self) -> Option<usize> {
+        self.coefficients.len().checked_sub(1)
+    }
+
+    pub fn coefficient(&self, degree: usize) -> Rational {
+        self.coefficients
+            .get(degree)
+            .copied()
+            .unwrap_or(Rational::ZERO)
+    }
+
+    pub fn evaluate(&self, x: Rational) -> Option<Rational> {
+        self.coefficients
+            .iter()
+            .rev()
+            .try_fold(Rational::ZERO, |value, &c| {
+                value.checked_mul(x)?.checked_add(c)
+            })
+    }
+
+    fn scale(&self, factor: Rational) -> Option<Self> {
+        let coefficients = self.coefficients.iter().map(|&c| c.checked_mul(factor));
+        Some(Self::new(coefficients.collect::<Option<_>>()?))
+    }
+
+    pub fn checked_add(&self, other: &Self) -> Option<Self> {
+        let len = self.coefficients.len().max(other.coefficients.len());
+        let coefficients = (0..len).map(|i| self.coefficient(i).checked_add(other.coefficient(i)));
+        Some(Self::new(coefficients.collect::<Option<_>>()?))
+    }
+
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        self.checked_add(&other.scale(-Rational::ONE)?)
+    }
+
+    pub fn checked_mul(&self, other: &Self) -> Option<Self> {
+        if self.is_zero() || other.is_zero() {
+            return Some(Self::new(Vec::new()));
+        }
+        let mut coefficients =
+            vec![Rational::ZERO; self.coefficients.len() + other.coefficients.len() - 1];
+        for (i, &a) in self.coefficients.iter().enumerate() {
+            for (j, &b) in other.coefficients.iter().enumerate() {
+                coefficients[i + j] = coefficients[i + j].checked_add(a.checked_mul(b)?)?;
             }
         }
+        Some(Self::new(coefficients))
+    }
+
+    /// Long division, returning the quotient and the remainder.
+    ///
+    /// Panics if the divisor is the zero polynomial.
+    pub fn div_rem(&self, divisor: &Self) -> Option<(Self, Self)> {
+        let divisor_degree = divisor.degree().expect("division by zero");
+        let lead = divisor.coefficient(divisor_degree);
+        let mut remainder = self.clone();
+        let mut quotient = Vec::new();
+        while let Some(degree) = remainder.degree().filter(|&d| d >= divisor_degree) {
+            let shift = degree - divisor_degree;
+            let factor = remainder.coefficient(degree).checked_div(lead)?;
+            if quotient.len() <= shift {
+                quotient.resize(shift + 1, Rational::ZERO);
+            }
+            quotient[shift] = factor;
+            let mut term = vec![Rational::ZERO; shift];
+            term.extend(divisor.scale(factor)?.coefficients);
+            remainder = remainder.checked_sub(&Self::new(term))?;
+        }
+        Some((Self::new(quotient), remainder))
+    }
+
+    /// The monic greatest common divisor of two polynomials.
+    pub fn gcd(&self, other: &Self) -> Option<Self> {
+        let (mut a, mut b) = (self.clone(), other.clone());
+        while !b.is_zero() {
+            let (_, remainder) = a.div_rem(&b)?;
+            a = b;
+            b = remainder;
+        }
+        match a.degree() {
+            Some(degree) => a.scale(a.coefficient(degree).recip()?),
+            None => Some(a),
+        }
     }
 }
 
-#[aoc(day21, part2)]
-pub fn part2(yells: &Input) -> i64 {
-    let (left, right) = if let Yell::Add(left, right) = yells.get("root").unwrap() {
-        (left, right)
-    } else {
-        panic!("unexpected root equation")
+/// The quotient of two polynomials, which is what an expression simplifies to.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RationalFunction {
+    pub numerator: Polynomial,
+    pub denominator: Polynomial,
+}
+
+impl RationalFunction {
+    fn new(mut numerator: Polynomial, mut denominator: Polynomial) -> Option<Self> {
+        // Cancel common factors, so e.g. `x * x / x` stays linear.
+        let gcd = numerator.gcd(&denominator)?;
+        if gcd.degree().map_or(false, |degree| degree > 0) {
+            numerator = numerator.div_rem(&gcd)?.0;
+            denominator = denominator.div_rem(&gcd)?.0;
+        }
+        // Keep constant denominators out of the way.
+        if denominator.degree() == Some(0) {
+            let factor = denominator.coefficient(0).recip()?;
+            return Some(Self {
+                numerator: numerator.scale(factor)?,
+                denominator: Polynomial::constant(Rational::ONE),
+            });
+        }
+        Some(Self {
+            numerator,
+            denominator,
+        })
+    }
+
+    fn polynomial(polynomial: Polynomial) -> Self {
+        Self {
+            numerator: polynomial,
+            denominator: Polynomial::constant(Rational::ONE),
+        }
+    }
+
+    fn add(&self, other: &Self) -> Result<Self, SolveError> {
+        let numerator = (self.numerator.checked_mul(&other.denominator))
+            .zip(other.numerator.checked_mul(&self.denominator))
+            .and_then(|(left, right)| left.checked_add(&right));
+        let denominator = self.denominator.checked_mul(&other.denominator);
+        numerator
+            .zip(denominator)
+            .and_then(|(numerator, denominator)| Self::new(numerator, denominator))
+            .ok_or(SolveError::Overflow)
+    }
+
+    fn sub(&self, other: &Self) -> Result<Self, SolveError> {
+        let negated = other
+            .numerator
+            .scale(-Rational::ONE)
+            .ok_or(SolveError::Overflow)?;
+        self.add(&Self {
+            numerator: negated,
+            denominator: other.denominator.clone(),
+        })
+    }
+
+    fn mul(&self, other: &Self) -> Result<Self, SolveError> {
+        let numerator = self.numerator.checked_mul(&other.numerator);
+        let denominator = self.denominator.checked_mul(&other.denominator);
+        numerator
+            .zip(denominator)
+            .and_then(|(numerator, denominator)| Self::new(numerator, denominator))
+            .ok_or(SolveError::Overflow)
+    }
+
+    fn div(&self, other: &Self) -> Result<Self, SolveError> {
+        if other.numerator.is_zero() {
+            return Err(SolveError::DivisionByZero);
+        }
+        self.mul(&Self {
+            numerator: other.denominator.clone(),
+            denominator: other.numerator.clone(),
+        })
+    }
+}
+
+impl Expression {
+    /// Evaluates the expression exactly.
+    pub fn evaluate(&self, x: Rational) -> Result<Rational, SolveError> {
+        let checked = |value: Option<Rational>| value.ok_or(SolveError::Overflow);
+        let mut values = Vec::<Rational>::with_capacity(self.nodes.len());
+        for &node in &self.nodes {
+            let value = match node {
+                Node::Number(value) => Rational::from(value),
+                Node::Variable => x,
+                Node::Add(left, right) => checked(values[left].checked_add(values[right]))?,
+                Node::Subtract(left, right) => checked(values[left].checked_sub(values[right]))?,
+                Node::Multiply(left, right) => checked(values[left].checked_mul(values[right]))?,
+                Node::Divide(left, right) => {
+                    if values[right].is_zero() {
+                        return Err(SolveError::DivisionByZero);
+                    }
+                    checked(values[left].checked_div(values[right]))?
+                }
+            };
+            values.push(value);
+        }
+        Ok(*values.last().expect("an expression has at least one node"))
+    }
+
+    /// Simplifies the expression to a single quotient of polynomials in the variable.
+    ///
+    /// Unlike the monkeys, this uses exact division.
+    pub fn simplify(&self) -> Result<RationalFunction, SolveError> {
+        let mut functions = Vec::<RationalFunction>::with_capacity(self.nodes.len());
+        for &node in &self.nodes {
+            let function = match node {
+                Node::Number(x) => {
+                    RationalFunction::polynomial(Polynomial::constant(Rational::from(x)))
+                }
+                Node::Variable => RationalFunction::polynomial(Polynomial::variable()),
+                Node::Add(left, right) => functions[left].add(&functions[right])?,
+                Node::Subtract(left, right) => functions[left].sub(&functions[right])?,
+                Node::Multiply(left, right) => functions[left].mul(&functions[right])?,
+                Node::Divide(left, right) => functions[left].div(&functions[right])?,
+            };
+            functions.push(function);
+        }
+        Ok(functions
+            .pop()
+            .expect("an expression has at least one node"))
+    }
+}
+
+/// Finds the value of `variable` for which both operands of `root` are equal.
+pub fn solve_equality(yells: &Input, root: &str, variable: &str) -> Result<Rational, SolveError> {
+    let (left, right) = yells
+        .get(root)
+        .ok_or_else(|| SolveError::UnknownMonkey(root.to_string()))?
+        .operands()
+        .ok_or_else(|| SolveError::NotAnEquation(root.to_string()))?;
+    let left_expression = expression(yells, left, variable)?;
+    let right_expression = expression(yells, right, variable)?;
+    let left = left_expression.simplify()?;
+    let right = right_expression.simplify()?;
+    // a / b = c / d  <=>  a * d - c * b = 0, as long as b and d aren't zero.
+    let equation = (left.numerator.checked_mul(&right.denominator))
+        .zip(right.numerator.checked_mul(&left.denominator))
+        .and_then(|(left, right)| left.checked_sub(&right))
+        .ok_or(SolveError::Overflow)?;
+    let solution = match equation.degree() {
+        None => return Err(SolveError::InfinitelyManySolutions),
+        Some(0) => return Err(SolveError::NoSolution),
+        Some(1) => (equation.coefficient(0).checked_neg())
+            .and_then(|c| c.checked_div(equation.coefficient(1)))
+            .ok_or(SolveError::Overflow)?,
+        Some(degree) => return Err(SolveError::NonLinear(degree)),
     };
-    // One side of the equality depends on "humn", the other side doesn't.
-    let (dependent, independent) =
-        match (depends_on_humn(left, yells), depends_on_humn(right, yells)) {
-            (true, false) => (left, right),
-            (false, true) => (right, left),
-            (true, true) => panic!("both sides depend on humn"),
-            (false, false) => panic!("no side depends on humn"),
+    for expression in [left_expression, right_expression] {
+        match expression.evaluate(solution) {
+            // The only solution would divide by zero somewhere, even if it cancelled out above.
+            Err(SolveError::DivisionByZero) => return Err(SolveError::NoSolution),
+            result => result?,
         };
-    // Compute the value of the independent side
-    let mut cache = HashMap::new();
-    let value = solve(independent, yells, &mut cache);
-    // Set the dependent side equal to the same value and solve for "humn"
-    solve_for_humn(dependent, value, yells, &mut cache)
+    }
+    Ok(solution)
+}
+
+#[aoc(day21, part2)]
+pub fn part2(yells: &Input) -> Result<i64, SolveError> {
+    let solution = solve_equality(yells, "root", "humn")?;
+    solution
+        .to_integer()
+        .and_then(|x| i64::try_from(x).ok())
+        .ok_or(SolveError::NotAnInteger(solution))
 }
 
 #[cfg(test)]
@@ -169,13 +691,152 @@ hmdt: 32"
 
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
-        assert_eq!(part1(&input), 152);
+        let input = input_generator(&TEST_INPUT).unwrap();
+        assert_eq!(part1(&input), Ok(152));
     }
 
     #[test]
     fn test_part2() {
-        let input = input_generator(&TEST_INPUT);
-        assert_eq!(part2(&input), 301);
+        let input = input_generator(&TEST_INPUT).unwrap();
+        assert_eq!(part2(&input), Ok(301));
+    }
+
+    #[test]
+    fn test_undefined_monkey() {
+        let input = TEST_INPUT.replace("hmdt: 32", "hmdt: 32\nzzzz: hmdt + qqqq");
+        let err = input_generator(&input).unwrap_err();
+        assert_eq!((err.line, err.column), (16, 14));
+        assert_eq!(err.message, "`zzzz` waits for undefined monkey `qqqq`");
+        let input = TEST_INPUT.replace("dbpl: 5", "dbpl: 5\ndbpl: 6");
+        let err = input_generator(&input).unwrap_err();
+        assert_eq!(
+            (err.line, err.message.as_str()),
+            (3, "duplicate monkey `dbpl`")
+        );
+    }
+
+    #[test]
+    fn test_part1_errors() {
+        let input = input_generator("root: a / b\na: 1\nb: 0").unwrap();
+        assert_eq!(part1(&input), Err(SolveError::DivisionByZero));
+        let input = input_generator("root: a * a\na: 9999999999").unwrap();
+        assert_eq!(part1(&input), Err(SolveError::Overflow));
+        // i64::MIN / -1
+        let input = "root: a / b; a: c - e; b: z - e; c: z - d; d: 9223372036854775807; e: 1; z: 0";
+        let input = input_generator(&input.replace("; ", "\n")).unwrap();
+        assert_eq!(part1(&input), Err(SolveError::Overflow));
+    }
+
+    #[test]
+    fn test_cycle() {
+        let input = TEST_INPUT.replace("hmdt: 32", "hmdt: sjmn + zczc");
+        let err = input_generator(&input).unwrap_err();
+        assert_eq!(
+            err.message,
+            "dependency cycle: drzm -> hmdt -> sjmn -> drzm"
+        );
+        assert_eq!(err.text, "drzm: hmdt - zczc");
+        let yells = Input::from([("a".to_string(), Yell::Add("a".to_string(), "a".to_string()))]);
+        assert_eq!(
+            validate(&yells),
+            Err(ValidationError::Cycle(vec![
+                "a".to_string(),
+                "a".to_string()
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let input = input_generator(&TEST_INPUT).unwrap();
+        let dot = to_dot(&input, "humn", "root");
+        assert!(dot.starts_with("digraph monkeys {\n"));
+        assert!(dot.contains("    \"humn\" [label=\"humn\\n5\", color=red, penwidth=2];\n"));
+        assert!(
+            dot.contains("    \"ptdq\" [label=\"ptdq\\nhumn - dvpt\", color=red, penwidth=2];\n")
+        );
+        assert!(dot.contains("    \"dvpt\" [label=\"dvpt\\n3\"];\n"));
+        assert!(dot.contains("    \"humn\" -> \"ptdq\" [color=red, penwidth=2];\n"));
+        assert!(dot.contains("    \"dvpt\" -> \"ptdq\";\n"));
+        assert!(dot.contains("    \"sjmn\" -> \"root\";\n"));
+        let highlighted_edges = dot
+            .lines()
+            .filter(|line| line.contains("->") && line.contains("red"));
+        // humn -> ptdq -> lgvd -> cczh -> pppw -> root
+        assert_eq!(highlighted_edges.count(), 5);
+    }
+
+    fn solve_root(input: &str) -> Result<Rational, SolveError> {
+        let input = input_generator(&input.replace("; ", "\n")).unwrap();
+        solve_equality(&input, "root", "humn")
+    }
+
+    #[test]
+    fn test_solve_equality() {
+        // humn on both sides
+        let solution = solve_root("root: a + b; a: humn * c; b: humn + d; c: 3; d: 4; humn: 0");
+        assert_eq!(solution, Ok(Rational::from(2)));
+        // humn in a denominator
+        let solution = solve_root("root: a + b; a: c / humn; b: 3; c: 6; humn: 0");
+        assert_eq!(solution, Ok(Rational::from(2)));
+        let solution = solve_root("root: a + b; a: humn * c; b: 3; c: 2; humn: 0");
+        assert_eq!(solution, Ok(Rational::new(3, 2)));
+        let input = input_generator("root: a + b\na: humn * c\nb: 3\nc: 2\nhumn: 0").unwrap();
+        assert_eq!(
+            part2(&input),
+            Err(SolveError::NotAnInteger(Rational::new(3, 2)))
+        );
+    }
+
+    #[test]
+    fn test_solve_equality_errors() {
+        let solution = solve_root("root: a + b; a: humn * c; b: humn + humn; c: 2; humn: 0");
+        assert_eq!(solution, Err(SolveError::InfinitelyManySolutions));
+        let solution = solve_root("root: a + b; a: humn + c; b: humn + d; c: 1; d: 0; humn: 0");
+        assert_eq!(solution, Err(SolveError::NoSolution));
+        // The only solution, humn = 0, divides by zero.
+        let solution = solve_root("root: a + b; a: humn / humn; b: humn + c; c: 1; humn: 0");
+        assert_eq!(solution, Err(SolveError::NoSolution));
+        let solution = solve_root("root: a + b; a: humn * humn; b: 4; humn: 0");
+        assert_eq!(solution, Err(SolveError::NonLinear(2)));
+        let solution = solve_root("root: a + b; a: c / humn; b: 4; c: humn * humn; humn: 0");
+        assert_eq!(solution, Ok(Rational::from(4)));
+        let solution = solve_root("root: a + b; a: humn / c; b: 4; c: 0; humn: 0");
+        assert_eq!(solution, Err(SolveError::DivisionByZero));
+        let solution = solve_root("root: 1; humn: 0");
+        assert_eq!(solution, Err(SolveError::NotAnEquation("root".to_string())));
+    }
+
+    #[test]
+    fn test_solve_equality_overflow() {
+        // a0 = humn * 10^9, a1 = a0 * 10^9, ... which outgrows an i128 after a few steps
+        let mut input = "root: a5 + b; b: 1; c: 1000000000; a0: humn * c; humn: 0".to_string();
+        for i in 1..=5 {
+            input += &format!("; a{}: a{} * c", i, i - 1);
+        }
+        assert_eq!(solve_root(&input), Err(SolveError::Overflow));
+    }
+
+    #[test]
+    fn test_deep_input() {
+        let mut input = "root: a49999 + z\nz: 0\none: 1\nhumn: 5\na0: humn + one".to_string();
+        for i in 1..50_000 {
+            input += &format!("\na{}: a{} + one", i, i - 1);
+        }
+        let input = input_generator(&input).unwrap();
+        assert_eq!(part1(&input), Ok(50_005));
+        assert_eq!(part2(&input), Ok(-50_000));
+        let dot = to_dot(&input, "humn", "root");
+        assert!(dot.contains("    \"a0\" -> \"a1\" [color=red, penwidth=2];\n"));
+    }
+
+    #[test]
+    fn test_shared_monkeys() {
+        // Every monkey waits for the previous one twice, so a tree would have 2^100 leaves.
+        let mut input = "root: a100 + z; z: 0; a0: humn - c; c: 3; humn: 0".to_string();
+        for i in 1..=100 {
+            input += &format!("; a{}: a{} + a{}", i, i - 1, i - 1);
+        }
+        assert_eq!(solve_root(&input), Ok(Rational::from(3)));
     }
 }
@@ -1,6 +1,8 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 
-use crate::util::Vector2D;
+use crate::util::{lines, Grid, Line, ParseError, Vector2D};
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Tile {
@@ -8,7 +10,7 @@ pub enum Tile {
     Wall,
 }
 
-pub type Board = HashMap<Vector2D, Tile>;
+pub type Board = Grid<Option<Tile>>;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Direction {
@@ -32,37 +34,49 @@ pub struct Input {
 }
 
 #[aoc_generator(day22)]
-pub fn input_generator(input: &str) -> Input {
-    let (board, path) = input.split_once("\n\n").unwrap();
-    let board = board
-        .lines()
-        .enumerate()
-        .flat_map(move |(y, line)| {
-            line.char_indices().flat_map(move |(x, c)| {
-                let pos = Vector2D::new(x as i32, y as i32);
-                match c {
-                    ' ' => None,
-                    '.' => Some((pos, Tile::Open)),
-                    '#' => Some((pos, Tile::Wall)),
-                    _ => panic!("unknown tile at {pos}: {c}"),
-                }
-            })
-        })
-        .collect();
-    let path = path
+pub fn input_generator(input: &str) -> Result<Input, ParseError> {
+    let lines = lines(22, input).collect::<Vec<_>>();
+    let separator = lines
+        .iter()
+        .position(|line| line.text.trim().is_empty())
+        .ok_or_else(|| {
+            let line = lines.last().copied().unwrap_or(Line::new(22, 1, ""));
+            line.error(
+                &line.text[line.text.len()..],
+                "expected a blank line before the path",
+            )
+        })?;
+    let board = Board::from_lines(&lines[..separator], |c| match c {
+        ' ' => Some(None),
+        '.' => Some(Some(Tile::Open)),
+        '#' => Some(Some(Tile::Wall)),
+        _ => None,
+    })?;
+    let line = lines[separator + 1..]
+        .iter()
+        .find(|line| !line.text.trim().is_empty())
+        .ok_or_else(|| {
+            let line = lines[separator];
+            line.error(line.text, "expected a path")
+        })?;
+    let path = line
+        .text
         .trim()
-        .split_inclusive(|c| c == 'L' || c == 'R')
-        .flat_map(|s| {
+        .split_inclusive(['L', 'R'])
+        .map(|s| {
             if let Some(s) = s.strip_suffix('L') {
-                vec![Step::Forward(s.parse().unwrap()), Step::Left]
+                Ok(vec![Step::Forward(line.parse(s)?), Step::Left])
             } else if let Some(s) = s.strip_suffix('R') {
-                vec![Step::Forward(s.parse().unwrap()), Step::Right]
+                Ok(vec![Step::Forward(line.parse(s)?), Step::Right])
             } else {
-                vec![Step::Forward(s.parse().unwrap())]
+                Ok(vec![Step::Forward(line.parse(s)?)])
             }
         })
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .flatten()
         .collect();
-    Input { board, path }
+    Ok(Input { board, path })
 }
 
 impl Direction {
@@ -75,6 +89,16 @@ impl Direction {
         }
     }
 
+    /// The symbol used for this direction when drawing the trail.
+    fn arrow(self) -> char {
+        match self {
+            Direction::Up => '^',
+            Direction::Left => '<',
+            Direction::Down => 'v',
+            Direction::Right => '>',
+        }
+    }
+
     fn turn_left(self) -> Self {
         match self {
             Direction::Up => Direction::Left,
@@ -94,11 +118,14 @@ impl Direction {
     }
 }
 
+fn tile_at(board: &Board, pos: Vector2D) -> Option<Tile> {
+    board.get(pos).copied().flatten()
+}
+
 fn find_start(board: &Board) -> Vector2D {
-    let (&start, _) = board
+    let (start, _) = board
         .iter()
-        .filter(|(pos, tile)| pos.y() == 0 && tile == &&Tile::Open)
-        .min_by_key(|(pos, _)| pos.x())
+        .find(|&(pos, tile)| pos.y() == 0 && tile == &Some(Tile::Open))
         .unwrap();
     start
 }
@@ -106,7 +133,7 @@ fn find_start(board: &Board) -> Vector2D {
 fn find_opposite_edge(board: &Board, start: Vector2D, dir: Direction) -> Vector2D {
     let mut pos = start;
     let step = dir.turn_left().turn_left().step();
-    while board.contains_key(&(pos + step)) {
+    while tile_at(board, pos + step).is_some() {
         pos += step;
     }
     pos
@@ -116,40 +143,93 @@ fn final_password(pos: Vector2D, dir: Direction) -> i32 {
     (pos.y() + 1) * 1000 + (pos.x() + 1) * 4 + (dir as i32)
 }
 
-#[aoc(day22, part1)]
-pub fn part1(input: &Input) -> i32 {
-    let start = find_start(&input.board);
-    let mut pos = start;
+/// How to continue when walking off the edge of the board.
+#[derive(Debug, Copy, Clone)]
+pub enum Wrapping<'a> {
+    /// Come back on the opposite side of the board.
+    Flat,
+    /// Continue on the connected face of the cube.
+    Cube(&'a Cube),
+}
+
+impl Wrapping<'_> {
+    fn wrap(self, board: &Board, pos: Vector2D, dir: Direction) -> (Vector2D, Direction) {
+        match self {
+            Wrapping::Flat => (find_opposite_edge(board, pos, dir), dir),
+            Wrapping::Cube(cube) => cube.find_connected_edge(pos, dir),
+        }
+    }
+}
+
+/// Follows the path, and returns every position and direction along the way:
+/// the start, every tile we moved to, and every turn.
+pub fn replay(input: &Input, wrapping: Wrapping) -> Vec<(Vector2D, Direction)> {
+    let mut pos = find_start(&input.board);
     let mut dir = Direction::Right;
+    let mut trail = vec![(pos, dir)];
     for &step in &input.path {
         match step {
             Step::Forward(amount) => {
                 'forward: for _ in 0..amount {
                     let mut next_pos = pos + dir.step();
-                    let next_tile = match input.board.get(&next_pos) {
+                    let mut next_dir = dir;
+                    let next_tile = match tile_at(&input.board, next_pos) {
                         Some(tile) => tile,
                         None => {
-                            next_pos = find_opposite_edge(&input.board, pos, dir);
-                            input.board.get(&next_pos).unwrap()
+                            (next_pos, next_dir) = wrapping.wrap(&input.board, pos, dir);
+                            tile_at(&input.board, next_pos).unwrap()
                         }
                     };
                     match next_tile {
-                        Tile::Open => pos = next_pos,
+                        Tile::Open => {
+                            pos = next_pos;
+                            dir = next_dir;
+                        }
                         Tile::Wall => {
                             break 'forward;
                         }
                     }
+                    trail.push((pos, dir));
                 }
             }
-            Step::Left => dir = dir.turn_left(),
-            Step::Right => dir = dir.turn_right(),
+            Step::Left => {
+                dir = dir.turn_left();
+                trail.push((pos, dir));
+            }
+            Step::Right => {
+                dir = dir.turn_right();
+                trail.push((pos, dir));
+            }
         }
     }
+    trail
+}
+
+/// Draws the board like the puzzle does, marking each tile on the trail
+/// with the direction we last faced there.
+pub fn render(board: &Board, trail: &[(Vector2D, Direction)]) -> String {
+    let mut picture = board.map(|tile| match tile {
+        None => ' ',
+        Some(Tile::Open) => '.',
+        Some(Tile::Wall) => '#',
+    });
+    for &(pos, dir) in trail {
+        *picture.get_mut(pos).unwrap() = dir.arrow();
+    }
+    picture
+        .rows()
+        .map(|row| row.iter().collect::<String>().trim_end().to_string() + "\n")
+        .collect()
+}
+
+#[aoc(day22, part1)]
+pub fn part1(input: &Input) -> i32 {
+    let &(pos, dir) = replay(input, Wrapping::Flat).last().unwrap();
     final_password(pos, dir)
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-enum Face {
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Face {
     Front,
     Left,
     Back,
@@ -172,12 +252,12 @@ impl Face {
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-struct Orientation {
+pub struct Orientation {
     /// The face we're looking at.
-    facing: Face,
+    pub facing: Face,
     /// The face that's on the top edge of the face we're looking at.
     /// Cannot be the same face, and cannot be the opposite face.
-    up: Face,
+    pub up: Face,
 }
 
 impl Orientation {
@@ -240,119 +320,224 @@ impl Orientation {
     }
 }
 
-// Map from (position / cube side length) to face orientation
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CubeError {
+    /// The number of tiles isn't six times a square number.
+    TileCount(usize),
+    /// The tiles don't form six square faces with the given side length.
+    Misaligned { side: i32, faces: usize },
+    /// The faces aren't all connected to each other.
+    Disconnected,
+    /// The faces are connected, but more than one of them would fold onto the given face.
+    Overlapping(Face),
+}
+
+impl Display for CubeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CubeError::TileCount(count) => write!(
+                f,
+                "{} tiles cannot form the 6 square faces of a cube",
+                count
+            ),
+            CubeError::Misaligned { side, faces } => write!(
+                f,
+                "the tiles form {} faces of {}x{} instead of 6",
+                faces, side, side
+            ),
+            CubeError::Disconnected => write!(f, "the faces aren't connected"),
+            CubeError::Overlapping(face) => {
+                write!(f, "the faces don't fold into a cube: {:?} overlaps", face)
+            }
+        }
+    }
+}
+
+impl Error for CubeError {}
+
+// Map from face position (see `Cube::face_pos()`) to face orientation
 type CubeNet = HashMap<Vector2D, Orientation>;
 
-fn fold_cube(board: &Board, side: i32, start: Vector2D) -> CubeNet {
+/// The board, folded into a cube.
+#[derive(Debug, Clone)]
+pub struct Cube {
+    side: i32,
+    /// The top left corner of the grid of faces, which is not necessarily `(0, 0)`.
+    origin: Vector2D,
+    net: CubeNet,
+}
+
+impl Cube {
+    /// Folds the board into a cube, inferring the side length from the number of tiles.
+    ///
+    /// The face containing the start tile becomes the front face.
+    pub fn fold(board: &Board) -> Result<Self, CubeError> {
+        let tiles = board
+            .iter()
+            .filter(|(_, tile)| tile.is_some())
+            .map(|(pos, _)| pos)
+            .collect::<Vec<_>>();
+        let side = ((tiles.len() / 6) as f64).sqrt().round() as i32;
+        if side == 0 || (6 * side * side) as usize != tiles.len() {
+            return Err(CubeError::TileCount(tiles.len()));
+        }
+        // The leftmost and topmost tiles must be on the edge of a face.
+        let origin = tiles.iter().fold(tiles[0], |origin, tile| origin.min(tile));
+        let mut cube = Cube {
+            side,
+            origin,
+            net: CubeNet::new(),
+        };
+        let faces = tiles
+            .iter()
+            .map(|&tile| cube.face_pos(tile))
+            .collect::<HashSet<_>>();
+        // With exactly six faces, each face must be completely filled.
+        if faces.len() != 6 {
+            return Err(CubeError::Misaligned {
+                side,
+                faces: faces.len(),
+            });
+        }
+        cube.net = fold_net(&faces, cube.face_pos(find_start(board)))?;
+        Ok(cube)
+    }
+
+    pub fn side(&self) -> i32 {
+        self.side
+    }
+
+    /// The orientation of the face that contains the given position on the board.
+    ///
+    /// Panics if the position isn't on the board.
+    pub fn orientation_at(&self, pos: Vector2D) -> Orientation {
+        self.net[&self.face_pos(pos)]
+    }
+
+    /// The position of the face that contains the given position on the board.
+    fn face_pos(&self, pos: Vector2D) -> Vector2D {
+        (pos - self.origin).map(|c| c.div_euclid(self.side))
+    }
+
+    fn find_connected_edge(&self, pos: Vector2D, dir: Direction) -> (Vector2D, Direction) {
+        let side = self.side;
+        // Find the next face (and orientation) we need to move to.
+        let current_face = self.net[&self.face_pos(pos)];
+        let next_face = match dir {
+            Direction::Up => current_face.move_up(),
+            Direction::Right => current_face.move_right(),
+            Direction::Down => current_face.move_down(),
+            Direction::Left => current_face.move_left(),
+        };
+        // Find where this next face appears on the board, possibly with a different orientation.
+        let (&board_pos, &board_face) = self
+            .net
+            .iter()
+            .find(|(_, orientation)| orientation.facing == next_face.facing)
+            .unwrap();
+        // Get the relative position on the next face.
+        let mut next_pos = (pos + dir.step() - self.origin).map(|c| c.rem_euclid(side));
+        // Rotate until the next face aligns with the one on the board.
+        let mut next_face = next_face;
+        let mut next_dir = dir;
+        while next_face.up != board_face.up {
+            // Rotate face to the left.
+            next_face = next_face.rotate_left();
+            // Rotate relative position and direction to the right.
+            next_pos = next_pos.rotate_right() + Vector2D::new(side - 1, 0);
+            next_dir = next_dir.turn_right();
+        }
+        // Get the absolute position on the board.
+        let next_pos = self.origin + (board_pos * side) + next_pos;
+        (next_pos, next_dir)
+    }
+}
+
+/// Folds the given face positions into a cube, and returns the orientation of each face.
+fn fold_net(faces: &HashSet<Vector2D>, start: Vector2D) -> Result<CubeNet, CubeError> {
     let mut net = CubeNet::new();
-    let start_pos = start / side;
     let start_orientation = Orientation::new(Face::Front, Face::Top);
-    fold_cube_inner(board, side, start_pos, start_orientation, &mut net);
-    net
+    fold_net_inner(faces, start, start_orientation, &mut net);
+    if net.len() != faces.len() {
+        return Err(CubeError::Disconnected);
+    }
+    let mut positions = net.keys().collect::<Vec<_>>();
+    positions.sort_unstable_by_key(|pos| (pos.y(), pos.x()));
+    let mut folded = HashSet::new();
+    for pos in positions {
+        let facing = net[pos].facing;
+        if !folded.insert(facing) {
+            return Err(CubeError::Overlapping(facing));
+        }
+    }
+    Ok(net)
 }
 
-fn fold_cube_inner(
-    board: &Board,
-    side: i32,
+fn fold_net_inner(
+    faces: &HashSet<Vector2D>,
     pos: Vector2D,
     orientation: Orientation,
     net: &mut CubeNet,
 ) {
     net.insert(pos, orientation);
     let pos_up = pos + Direction::Up.step();
-    if !net.contains_key(&pos_up) && board.contains_key(&(pos_up * side)) {
-        fold_cube_inner(board, side, pos_up, orientation.move_up(), net);
+    if !net.contains_key(&pos_up) && faces.contains(&pos_up) {
+        fold_net_inner(faces, pos_up, orientation.move_up(), net);
     }
     let pos_down = pos + Direction::Down.step();
-    if !net.contains_key(&pos_down) && board.contains_key(&(pos_down * side)) {
-        fold_cube_inner(board, side, pos_down, orientation.move_down(), net);
+    if !net.contains_key(&pos_down) && faces.contains(&pos_down) {
+        fold_net_inner(faces, pos_down, orientation.move_down(), net);
     }
     let pos_left = pos + Direction::Left.step();
-    if !net.contains_key(&pos_left) && board.contains_key(&(pos_left * side)) {
-        fold_cube_inner(board, side, pos_left, orientation.move_left(), net);
+    if !net.contains_key(&pos_left) && faces.contains(&pos_left) {
+        fold_net_inner(faces, pos_left, orientation.move_left(), net);
     }
     let pos_right = pos + Direction::Right.step();
-    if !net.contains_key(&pos_right) && board.contains_key(&(pos_right * side)) {
-        fold_cube_inner(board, side, pos_right, orientation.move_right(), net);
+    if !net.contains_key(&pos_right) && faces.contains(&pos_right) {
+        fold_net_inner(faces, pos_right, orientation.move_right(), net);
     }
 }
 
-fn find_connected_edge(
-    net: &CubeNet,
-    side: i32,
-    pos: Vector2D,
-    dir: Direction,
-) -> (Vector2D, Direction) {
-    // Find the next face (and orientation) we need to move to.
-    let current_face = *net.get(&(pos / side)).unwrap();
-    let next_face = match dir {
-        Direction::Up => current_face.move_up(),
-        Direction::Right => current_face.move_right(),
-        Direction::Down => current_face.move_down(),
-        Direction::Left => current_face.move_left(),
-    };
-    // Find where this next face appears on the board, possibly with a different orientation.
-    let (&board_pos, &board_face) = net
-        .iter()
-        .find(|(_, orientation)| orientation.facing == next_face.facing)
-        .unwrap();
-    // Get the relative position on the next face.
-    let next_pos = pos + dir.step();
-    let mut next_pos = Vector2D::new((next_pos.x() + side) % side, (next_pos.y() + side) % side);
-    // Rotate until the next face aligns with the one on the board.
-    let mut next_face = next_face;
-    let mut next_dir = dir;
-    while next_face.up != board_face.up {
-        // Rotate face to the left.
-        next_face = next_face.rotate_left();
-        // Rotate relative position and direction to the right.
-        next_pos = Vector2D::new((side - 1) - next_pos.y(), next_pos.x());
-        next_dir = next_dir.turn_right();
-    }
-    // Get the absolute position on the board.
-    let next_pos = (board_pos * side) + next_pos;
-    (next_pos, next_dir)
+/// A step on the trail, along with where on the cube it is.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct CubeStep {
+    pub pos: Vector2D,
+    pub dir: Direction,
+    /// The face we're on, and which face is up when the face is viewed as it is on the board.
+    pub orientation: Orientation,
 }
 
-fn solve_cube(input: &Input, side: i32) -> i32 {
-    let start = find_start(&input.board);
-    let net = fold_cube(&input.board, side, start);
-    let mut pos = start;
-    let mut dir = Direction::Right;
-    for &step in &input.path {
-        match step {
-            Step::Forward(amount) => {
-                'forward: for _ in 0..amount {
-                    let mut next_pos = pos + dir.step();
-                    let mut next_dir = dir;
-                    let next_tile = match input.board.get(&next_pos) {
-                        Some(tile) => tile,
-                        None => {
-                            (next_pos, next_dir) = find_connected_edge(&net, side, pos, dir);
-                            input.board.get(&next_pos).unwrap()
-                        }
-                    };
-                    match next_tile {
-                        Tile::Open => {
-                            pos = next_pos;
-                            dir = next_dir;
-                        }
-                        Tile::Wall => {
-                            break 'forward;
-                        }
-                    }
-                }
-            }
-            Step::Left => dir = dir.turn_left(),
-            Step::Right => dir = dir.turn_right(),
-        }
+impl Display for CubeStep {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {:?} (up: {:?})",
+            self.pos,
+            self.dir.arrow(),
+            self.orientation.facing,
+            self.orientation.up
+        )
     }
-    final_password(pos, dir)
+}
+
+/// Like `replay()` on the cube, but also reports which face every step is on.
+pub fn replay_cube(input: &Input) -> Result<Vec<CubeStep>, CubeError> {
+    let cube = Cube::fold(&input.board)?;
+    Ok(replay(input, Wrapping::Cube(&cube))
+        .into_iter()
+        .map(|(pos, dir)| CubeStep {
+            pos,
+            dir,
+            orientation: cube.orientation_at(pos),
+        })
+        .collect())
 }
 
 #[aoc(day22, part2)]
-pub fn part2(input: &Input) -> i32 {
-    solve_cube(input, 50)
+pub fn part2(input: &Input) -> Result<i32, CubeError> {
+    let cube = Cube::fold(&input.board)?;
+    let &(pos, dir) = replay(input, Wrapping::Cube(&cube)).last().unwrap();
+    Ok(final_password(pos, dir))
 }
 
 #[cfg(test)]
@@ -365,21 +550,163 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part1(&input), 6032);
     }
 
     #[test]
     fn test_part2() {
-        let input = input_generator(&TEST_INPUT);
-        assert_eq!(solve_cube(&input, 4), 5031);
+        let input = input_generator(&TEST_INPUT).unwrap();
+        assert_eq!(part2(&input), Ok(5031));
+    }
+
+    #[test]
+    fn test_render() {
+        let input = input_generator(&TEST_INPUT).unwrap();
+        let trail = replay(&input, Wrapping::Flat);
+        assert_eq!(trail[0], (Vector2D::new(8, 0), Direction::Right));
+        assert_eq!(
+            render(&input.board, &trail),
+            "        >>v#
+        .#v.
+        #.v.
+        ..v.
+...#...v..v#
+>>>v...>#.>>
+..#v...#....
+...>>>>v..#.
+        ...#....
+        .....#..
+        .#......
+        ......#.
+"
+        );
+    }
+
+    #[test]
+    fn test_replay_cube() {
+        let input = input_generator(&TEST_INPUT).unwrap();
+        let steps = replay_cube(&input).unwrap();
+        // The wrap from A to B in the puzzle statement.
+        let b = steps
+            .iter()
+            .position(|step| step.pos == Vector2D::new(14, 8))
+            .unwrap();
+        assert_eq!(steps[b - 1].to_string(), "(11, 5) > Bottom (up: Front)");
+        assert_eq!(steps[b].to_string(), "(14, 8) v Right (up: Bottom)");
+        let last = steps.last().unwrap();
+        assert_eq!((last.pos, last.dir), (Vector2D::new(6, 4), Direction::Up));
+    }
+
+    #[test]
+    fn test_part2_offset() {
+        // Shift the board so the faces no longer start at a multiple of the side length.
+        let (board, path) = TEST_INPUT.split_once("\n\n").unwrap();
+        let board = board
+            .lines()
+            .map(|line| format!("  {}", line))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let input = input_generator(&format!("{}\n\n{}", board, path)).unwrap();
+        let cube = Cube::fold(&input.board).unwrap();
+        assert_eq!((cube.side(), cube.origin), (4, Vector2D::new(2, 0)));
+        assert_eq!(part2(&input), Ok(5031 + 2 * 4));
+    }
+
+    fn fold_board(board: &str) -> Result<Cube, CubeError> {
+        let input = input_generator(&format!("{}\n\n1", board)).unwrap();
+        Cube::fold(&input.board)
+    }
+
+    #[test]
+    fn test_invalid_nets() {
+        assert_eq!(
+            fold_board(&".".repeat(24)).unwrap_err(),
+            CubeError::Misaligned { side: 2, faces: 12 }
+        );
+        assert_eq!(fold_board(".....").unwrap_err(), CubeError::TileCount(5));
+        assert_eq!(
+            fold_board("......").unwrap_err(),
+            CubeError::Overlapping(Face::Front)
+        );
+        assert_eq!(fold_board("... ...").unwrap_err(), CubeError::Disconnected);
+        assert_eq!(
+            fold_board("..  \n  ..\n.  .").unwrap_err(),
+            CubeError::Disconnected
+        );
+        assert!(fold_board("..  \n ...\n  . ").is_ok());
+    }
+
+    /// All polyominoes of the given size, up to rotations and reflections.
+    fn free_polyominoes(size: usize) -> HashSet<Vec<Vector2D>> {
+        let canonical = |cells: &[Vector2D]| {
+            let transforms: [fn(Vector2D) -> Vector2D; 8] = [
+                |p| p,
+                |p| Vector2D::new(-p.x(), p.y()),
+                |p| Vector2D::new(p.x(), -p.y()),
+                |p| -p,
+                |p| Vector2D::new(p.y(), p.x()),
+                |p| Vector2D::new(-p.y(), p.x()),
+                |p| Vector2D::new(p.y(), -p.x()),
+                |p| Vector2D::new(-p.y(), -p.x()),
+            ];
+            transforms
+                .iter()
+                .map(|transform| {
+                    let cells = cells.iter().map(|&p| transform(p)).collect::<Vec<_>>();
+                    let min = cells.iter().fold(cells[0], |min, p| min.min(p));
+                    let mut cells = cells
+                        .iter()
+                        .map(|&p| p - min)
+                        .map(|p| (p.y(), p.x()))
+                        .collect::<Vec<_>>();
+                    cells.sort_unstable();
+                    cells
+                })
+                .min()
+                .unwrap()
+                .into_iter()
+                .map(|(y, x)| Vector2D::new(x, y))
+                .collect::<Vec<_>>()
+        };
+        let mut shapes = HashSet::from([vec![Vector2D::new(0, 0)]]);
+        for _ in 1..size {
+            let mut next = HashSet::new();
+            for shape in &shapes {
+                for cell in shape.iter().flat_map(|p| p.neighbours()) {
+                    if !shape.contains(&cell) {
+                        let mut grown = shape.clone();
+                        grown.push(cell);
+                        next.insert(canonical(&grown));
+                    }
+                }
+            }
+            shapes = next;
+        }
+        shapes
+    }
+
+    #[test]
+    fn test_eleven_nets() {
+        let hexominoes = free_polyominoes(6);
+        assert_eq!(hexominoes.len(), 35);
+        let nets = hexominoes
+            .iter()
+            .filter(|shape| {
+                let faces = shape.iter().copied().collect::<HashSet<_>>();
+                fold_net(&faces, shape[0]).is_ok()
+            })
+            .count();
+        assert_eq!(nets, 11);
     }
 
     #[test]
     fn test_fold_cube() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
+        let cube = Cube::fold(&input.board).unwrap();
+        assert_eq!((cube.side(), cube.origin), (4, Vector2D::new(0, 0)));
         assert_eq!(
-            fold_cube(&input.board, 4, Vector2D::new(8, 0)),
+            cube.net,
             CubeNet::from_iter([
                 (
                     Vector2D::new(2, 0),
@@ -1,22 +1,24 @@
-use std::collections::{HashMap, HashSet};
+use crate::util::{BoundingBox, Grid, ParseError, Vector2D};
 
-use crate::util::Vector2D;
-
-type Grid = HashSet<Vector2D>;
+/// Whether each position is occupied by an Elf.
+/// The grid grows as Elves spread out.
+type Elves = Grid<bool>;
 
 #[aoc_generator(day23)]
-pub fn input_generator(input: &str) -> Grid {
-    input
-        .lines()
-        .enumerate()
-        .flat_map(move |(y, line)| {
-            line.char_indices().filter_map(move |(x, c)| match c {
-                '.' => None,
-                '#' => Some(Vector2D::new(x as i32, y as i32)),
-                _ => panic!("unexpected char: {c}"),
-            })
-        })
-        .collect()
+pub fn input_generator(input: &str) -> Result<Elves, ParseError> {
+    Grid::parse(23, input, |c| match c {
+        '.' => Some(false),
+        '#' => Some(true),
+        _ => None,
+    })
+}
+
+fn is_elf(grid: &Elves, pos: Vector2D) -> bool {
+    grid.get(pos).copied().unwrap_or(false)
+}
+
+fn elves(grid: &Elves) -> impl Iterator<Item = Vector2D> + '_ {
+    grid.iter().filter(|&(_, &elf)| elf).map(|(pos, _)| pos)
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -63,13 +65,17 @@ impl Direction {
     }
 }
 
-fn step(grid: &Grid, directions: &[Direction]) -> (bool, Grid) {
-    let mut proposals = HashMap::<Vector2D, Vector2D>::new();
-    let mut nb_proposals = HashMap::<Vector2D, usize>::new();
+fn step(grid: &Elves, directions: &[Direction]) -> (bool, Elves) {
+    let mut proposals = Vec::<(Vector2D, Vector2D)>::new();
+    let mut nb_proposals = Grid::with_bounds(
+        grid.min() - Vector2D::new(1, 1),
+        grid.max() + Vector2D::new(1, 1),
+        0u8,
+    );
 
     // First half
-    for &elf in grid {
-        if elf.neighbours_diagonal().all(|pos| !grid.contains(&pos)) {
+    for elf in elves(grid) {
+        if elf.neighbours_diagonal().all(|pos| !is_elf(grid, pos)) {
             // If no other Elves are in one of those eight positions,
             // the Elf does not do anything during this round.
             continue;
@@ -79,15 +85,11 @@ fn step(grid: &Grid, directions: &[Direction]) -> (bool, Grid) {
         //   the Elf proposes moving north one step.
         // * ...
         for &dir in directions {
-            if dir
-                .edges()
-                .iter()
-                .all(|&step| !grid.contains(&(elf + step)))
-            {
+            if dir.edges().iter().all(|&step| !is_elf(grid, elf + step)) {
                 let next_pos = elf + dir.step();
                 // println!("{elf} proposes {dir:?} to {next_pos}");
-                proposals.insert(elf, next_pos);
-                *nb_proposals.entry(next_pos).or_default() += 1;
+                proposals.push((elf, next_pos));
+                nb_proposals[next_pos] += 1;
                 break;
             }
         }
@@ -100,57 +102,30 @@ fn step(grid: &Grid, directions: &[Direction]) -> (bool, Grid) {
     // Second half: each Elf moves to their proposed destination tile
     // if they were the only Elf to propose moving to that position.
     // If two or more Elves propose moving to the same position, none of those Elves move.
-    let new_grid = grid
-        .iter()
-        .map(|&elf| {
-            match proposals.get(&elf) {
-                Some(&next) if *nb_proposals.get(&next).unwrap() == 1 => {
-                    // Elf wants to move, and is the only one to move there
-                    next
-                }
-                _ => {
-                    // Elf cannot move, or doesn't want to move
-                    elf
-                }
-            }
-        })
-        .collect();
+    let mut new_grid = grid.clone();
+    for (elf, next) in proposals {
+        if nb_proposals[next] == 1 {
+            // Elf wants to move, and is the only one to move there
+            new_grid[elf] = false;
+            new_grid.grow_to_include(next, false);
+            new_grid[next] = true;
+        }
+    }
     (false, new_grid)
 }
 
-fn count_empty(grid: &Grid) -> i32 {
-    let min_x = grid.iter().map(|pos| pos.x()).min().unwrap();
-    let max_x = grid.iter().map(|pos| pos.x()).max().unwrap();
-    let min_y = grid.iter().map(|pos| pos.y()).min().unwrap();
-    let max_y = grid.iter().map(|pos| pos.y()).max().unwrap();
-    let width = max_x - min_x + 1;
-    let height = max_y - min_y + 1;
-    (width * height) - (grid.len() as i32)
+fn count_empty(grid: &Elves) -> i32 {
+    let bounds = BoundingBox::from_points(elves(grid)).unwrap();
+    bounds.volume() - (elves(grid).count() as i32)
 }
 
 #[allow(unused)]
-fn print_grid(grid: &Grid) {
-    let min_x = grid.iter().map(|pos| pos.x()).min().unwrap();
-    let max_x = grid.iter().map(|pos| pos.x()).max().unwrap();
-    let min_y = grid.iter().map(|pos| pos.y()).min().unwrap();
-    let max_y = grid.iter().map(|pos| pos.y()).max().unwrap();
-    for y in min_y..=max_y {
-        for x in min_x..=max_x {
-            print!(
-                "{}",
-                if grid.contains(&Vector2D::new(x, y)) {
-                    '#'
-                } else {
-                    '.'
-                }
-            );
-        }
-        println!();
-    }
+fn print_grid(grid: &Elves) {
+    println!("{}", grid.map(|&elf| if elf { '#' } else { '.' }));
 }
 
 #[aoc(day23, part1)]
-pub fn part1(input: &Grid) -> i32 {
+pub fn part1(input: &Elves) -> i32 {
     let mut grid = input.clone();
     let mut directions = [Direction::N, Direction::S, Direction::W, Direction::E];
     for _round in 1..=10 {
@@ -165,7 +140,7 @@ pub fn part1(input: &Grid) -> i32 {
 }
 
 #[aoc(day23, part2)]
-pub fn part2(input: &Grid) -> usize {
+pub fn part2(input: &Elves) -> usize {
     let mut grid = input.clone();
     let mut directions = [Direction::N, Direction::S, Direction::W, Direction::E];
     let mut round = 1;
@@ -206,19 +181,19 @@ mod tests {
 
     #[test]
     fn test_part1_small() {
-        let input = input_generator(&SMALL);
+        let input = input_generator(&SMALL).unwrap();
         assert_eq!(part1(&input), 25);
     }
 
     #[test]
     fn test_part1_large() {
-        let input = input_generator(&LARGE);
+        let input = input_generator(&LARGE).unwrap();
         assert_eq!(part1(&input), 110);
     }
 
     #[test]
     fn test_part2() {
-        let input = input_generator(&LARGE);
+        let input = input_generator(&LARGE).unwrap();
         assert_eq!(part2(&input), 20);
     }
 }
@@ -2,7 +2,7 @@ use std::iter::once;
 
 use pathfinding::prelude::astar;
 
-use crate::util::Vector2D;
+use crate::util::{lines, Grid, ParseError, Vector2D};
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 enum Direction {
@@ -15,6 +15,10 @@ enum Direction {
 pub struct Valley {
     width: i32,
     height: i32,
+    /// The walls around the valley, with the top-left corner of the valley's interior at (0, 0).
+    walls: Grid<bool>,
+    start: Vector2D,
+    goal: Vector2D,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
@@ -29,28 +33,69 @@ pub struct Input {
 }
 
 #[aoc_generator(day24)]
-pub fn input_generator(input: &str) -> Input {
-    let height = input.lines().count() as i32 - 2;
-    let width = input.lines().next().unwrap().len() as i32 - 2;
-    let valley = Valley { width, height };
-    let blizzards = input
-        .lines()
-        .enumerate()
-        .flat_map(move |(y, line)| {
-            line.char_indices().filter_map(move |(x, c)| {
-                let dir = match c {
-                    '^' => Direction::Up,
-                    'v' => Direction::Down,
-                    '<' => Direction::Left,
-                    '>' => Direction::Right,
-                    _ => return None,
-                };
-                let pos = Vector2D::new(x as i32 - 1, y as i32 - 1);
-                Some(Blizzard { pos, dir })
-            })
+pub fn input_generator(input: &str) -> Result<Input, ParseError> {
+    let lines = lines(24, input).collect::<Vec<_>>();
+    let height = lines.len() as i32 - 2;
+    let width = lines.first().map_or(0, |line| line.text.len() as i32) - 2;
+    if width <= 0 || height <= 0 {
+        return Err(ParseError::new(24, 1, 1, "", "valley is too small"));
+    }
+    // The valley must be surrounded by walls, with a single opening at the top and bottom
+    let mut openings = Vec::new();
+    for (y, line) in lines.iter().enumerate() {
+        if line.text.len() != lines[0].text.len() {
+            let expected = lines[0].text.len();
+            return Err(line.error(line.text, format!("expected {} tiles", expected)));
+        }
+        let outer_row = y == 0 || y == lines.len() - 1;
+        for (x, c) in line.text.char_indices() {
+            let wall = outer_row || x == 0 || x == line.text.len() - 1;
+            let corner = outer_row && (x == 0 || x == line.text.len() - 1);
+            let error = match c {
+                '#' if !wall => "wall inside valley",
+                '.' if outer_row && !corner => {
+                    if openings.last().map(|&(_, row)| row) == Some(y) {
+                        "expected a single opening in the wall"
+                    } else {
+                        openings.push((Vector2D::new(x as i32 - 1, y as i32 - 1), y));
+                        continue;
+                    }
+                }
+                '.' if wall => "expected a wall",
+                '^' | 'v' | '<' | '>' if wall => "blizzard inside wall",
+                _ => continue,
+            };
+            return Err(line.error(&line.text[x..], error));
+        }
+        if outer_row && openings.last().map(|&(_, row)| row) != Some(y) {
+            return Err(line.error(line.text, "expected an opening in the wall"));
+        }
+    }
+    let (start, goal) = (openings[0].0, openings[1].0);
+    let map = Grid::from_lines(&lines, |c| "#.^v<>".contains(c).then_some(c))?
+        .with_origin(Vector2D::new(-1, -1));
+    let walls = map.map(|&c| c == '#');
+    let valley = Valley {
+        width,
+        height,
+        walls,
+        start,
+        goal,
+    };
+    let blizzards = map
+        .iter()
+        .filter_map(|(pos, &c)| {
+            let dir = match c {
+                '^' => Direction::Up,
+                'v' => Direction::Down,
+                '<' => Direction::Left,
+                '>' => Direction::Right,
+                _ => return None,
+            };
+            Some(Blizzard { pos, dir })
         })
         .collect();
-    Input { valley, blizzards }
+    Ok(Input { valley, blizzards })
 }
 
 impl Direction {
@@ -75,21 +120,15 @@ impl Direction {
 
 impl Valley {
     fn start(&self) -> Vector2D {
-        Vector2D::new(0, -1)
+        self.start
     }
 
     fn goal(&self) -> Vector2D {
-        Vector2D::new(self.width - 1, self.height)
+        self.goal
     }
 
     fn is_wall(&self, pos: &Vector2D) -> bool {
-        if pos.y() == -1 {
-            *pos != self.start()
-        } else if pos.y() == self.height {
-            *pos != self.goal()
-        } else {
-            pos.x() < 0 || pos.x() >= self.width
-        }
+        self.walls.get(*pos).copied().unwrap_or(true)
     }
 
     #[allow(unused)]
@@ -237,13 +276,54 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part1(&input), 18);
     }
 
     #[test]
     fn test_part2() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part2(&input), 54);
     }
+
+    #[test]
+    fn test_invalid_valleys() {
+        let error = |input: &str| {
+            let err = input_generator(input).err().unwrap();
+            (err.line, err.column, err.message)
+        };
+        let message = |line, column, message: &str| (line, column, message.to_string());
+        assert_eq!(
+            error("#.###\n#.>.#\n#.##\n###.#"),
+            message(3, 1, "expected 5 tiles")
+        );
+        assert_eq!(
+            error("#.###\n#.>..#\n###.#"),
+            message(2, 1, "expected 5 tiles")
+        );
+        assert_eq!(
+            error("#.###\n#..<<\n###.#"),
+            message(2, 5, "blizzard inside wall")
+        );
+        assert_eq!(
+            error("#.###\n.....\n###.#"),
+            message(2, 1, "expected a wall")
+        );
+        assert_eq!(
+            error("#.###\n#.#.#\n###.#"),
+            message(2, 3, "wall inside valley")
+        );
+        assert_eq!(
+            error("#..##\n#...#\n###.#"),
+            message(1, 3, "expected a single opening in the wall")
+        );
+        assert_eq!(
+            error("#.###\n#...#\n#####"),
+            message(3, 1, "expected an opening in the wall")
+        );
+        assert_eq!(
+            error(".####\n#...#\n###.#"),
+            message(1, 1, "expected a wall")
+        );
+    }
 }
@@ -1,6 +1,8 @@
 use std::fmt::{Display, Formatter, Write};
 use std::str::FromStr;
 
+use crate::util::{lines, Line, ParseError};
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Digit {
     Two = 2,
@@ -10,15 +12,17 @@ pub enum Digit {
     DoubleMinus = -2,
 }
 
-impl From<char> for Digit {
-    fn from(c: char) -> Digit {
+impl TryFrom<char> for Digit {
+    type Error = ();
+
+    fn try_from(c: char) -> Result<Digit, ()> {
         match c {
-            '2' => Digit::Two,
-            '1' => Digit::One,
-            '0' => Digit::Zero,
-            '-' => Digit::Minus,
-            '=' => Digit::DoubleMinus,
-            c => panic!("invalid snafu digit: {c}"),
+            '2' => Ok(Digit::Two),
+            '1' => Ok(Digit::One),
+            '0' => Ok(Digit::Zero),
+            '-' => Ok(Digit::Minus),
+            '=' => Ok(Digit::DoubleMinus),
+            _ => Err(()),
         }
     }
 }
@@ -41,10 +45,16 @@ pub struct Snafu {
 }
 
 impl FromStr for Snafu {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let digits = s.chars().map(Digit::from).collect::<Vec<_>>();
+        let line = Line::new(25, 1, s);
+        let digits = s
+            .char_indices()
+            .map(|(x, c)| {
+                Digit::try_from(c).map_err(|_| line.error(&s[x..], "invalid snafu digit"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
         Ok(Snafu { digits })
     }
 }
@@ -59,8 +69,8 @@ impl Display for Snafu {
 }
 
 #[aoc_generator(day25)]
-pub fn input_generator(input: &str) -> Vec<Snafu> {
-    input.lines().map(|line| line.parse().unwrap()).collect()
+pub fn input_generator(input: &str) -> Result<Vec<Snafu>, ParseError> {
+    lines(25, input).map(|line| line.parse_line()).collect()
 }
 
 impl From<&Snafu> for i64 {
@@ -148,7 +158,7 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part1(&input), "2=-1=0");
     }
 
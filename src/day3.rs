@@ -1,8 +1,24 @@
 use std::collections::HashSet;
 
+use crate::util::{lines, ParseError};
+
 #[aoc_generator(day3)]
-pub fn input_generator(input: &str) -> Vec<String> {
-    input.lines().map(|line| line.to_string()).collect()
+pub fn input_generator(input: &str) -> Result<Vec<String>, ParseError> {
+    lines(3, input)
+        .map(|line| {
+            if let Some((x, _)) = line
+                .text
+                .char_indices()
+                .find(|(_, c)| !c.is_ascii_alphabetic())
+            {
+                return Err(line.error(&line.text[x..], "invalid item type"));
+            }
+            if line.text.len() % 2 != 0 {
+                return Err(line.error(line.text, "compartments have different sizes"));
+            }
+            Ok(line.text.to_string())
+        })
+        .collect()
 }
 
 fn find_shared_item(rucksack: &str) -> char {
@@ -67,13 +83,13 @@ CrZsJsPPZsGzwwsLwLmpwMDw"
 
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part1(&input), 157);
     }
 
     #[test]
     fn test_part2() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part2(&input), 70);
     }
 }
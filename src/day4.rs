@@ -1,4 +1,4 @@
-use std::str::FromStr;
+use crate::util::{lines, Line, ParseError};
 
 #[derive(Debug, Copy, Clone)]
 pub struct Range {
@@ -6,24 +6,19 @@ pub struct Range {
     end: u32,
 }
 
-impl FromStr for Range {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (begin, end) = s.split_once('-').unwrap();
-        let begin = begin.parse().unwrap();
-        let end = end.parse().unwrap();
-        Ok(Range { begin, end })
-    }
+fn parse_range(line: &Line, s: &str) -> Result<Range, ParseError> {
+    let (begin, end) = line.split_once(s, "-")?;
+    let begin = line.parse(begin)?;
+    let end = line.parse(end)?;
+    Ok(Range { begin, end })
 }
 
 #[aoc_generator(day4)]
-pub fn input_generator(input: &str) -> Vec<(Range, Range)> {
-    input
-        .lines()
+pub fn input_generator(input: &str) -> Result<Vec<(Range, Range)>, ParseError> {
+    lines(4, input)
         .map(|line| {
-            let (left, right) = line.split_once(',').unwrap();
-            (left.parse().unwrap(), right.parse().unwrap())
+            let (left, right) = line.split_once(line.text, ",")?;
+            Ok((parse_range(&line, left)?, parse_range(&line, right)?))
         })
         .collect()
 }
@@ -74,13 +69,13 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part1(&input), 2);
     }
 
     #[test]
     fn test_part2() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part2(&input), 4);
     }
 }
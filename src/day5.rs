@@ -1,5 +1,7 @@
 use std::collections::{HashMap, VecDeque};
 
+use crate::util::{blocks, ParseError};
+
 pub type Stacks = HashMap<usize, VecDeque<char>>;
 
 #[derive(Debug, Clone)]
@@ -16,43 +18,65 @@ pub struct Move {
 }
 
 #[aoc_generator(day5)]
-pub fn input_generator(input: &str) -> Input {
-    let (stack_text, move_text) = input.split_once("\n\n").unwrap();
+pub fn input_generator(input: &str) -> Result<Input, ParseError> {
+    let blocks = blocks(5, input);
+    let (stack_lines, move_lines) = match &blocks[..] {
+        [stack_lines, move_lines] => (stack_lines, move_lines),
+        [stack_lines] => {
+            let line = stack_lines.last().unwrap();
+            return Err(line.error(&line.text[line.text.len()..], "expected moves"));
+        }
+        [_, _, extra, ..] => {
+            let line = extra[0];
+            return Err(line.error(line.text, "unexpected text after moves"));
+        }
+        [] => return Err(ParseError::new(5, 1, 1, "", "expected stacks")),
+    };
     let mut stacks = HashMap::new();
-    for mut line in stack_text.lines() {
-        if line.starts_with(" 1 ") {
+    for line in stack_lines {
+        if line.text.starts_with(" 1 ") {
             break;
         }
+        let mut rest = line.text;
         let mut i = 1usize;
-        while !line.is_empty() {
-            let (chunk, rest) = line.split_at(3);
+        while !rest.is_empty() {
+            let chunk = rest
+                .get(..3)
+                .ok_or_else(|| line.error(rest, "incomplete stack"))?;
             let stack: &mut VecDeque<char> = stacks.entry(i).or_default();
             if chunk == "   " {
                 // empty
-            } else if let Some(s) = chunk.strip_prefix('[') {
+            } else if let Some(c) = chunk.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
                 // crate
-                let c = s.chars().next().unwrap();
-                stack.push_back(c);
+                stack.push_back(line.parse(c)?);
             } else {
-                panic!("invalid stack: {}", line);
+                return Err(line.error(chunk, "invalid stack"));
             }
-            line = rest.strip_prefix(' ').unwrap_or_default();
+            rest = rest[3..].strip_prefix(' ').unwrap_or_default();
             i += 1;
         }
     }
-    let moves = move_text
-        .trim()
-        .lines()
-        .map(|line| match line.split(' ').collect::<Vec<_>>()[..] {
-            [_, amount, _, from, _, to, ..] => Move {
-                amount: amount.parse().unwrap(),
-                from: from.parse().unwrap(),
-                to: to.parse().unwrap(),
-            },
-            _ => panic!("invalid move: {}", line),
+    let moves = move_lines
+        .iter()
+        .map(|line| {
+            let s = line.strip_prefix(line.text.trim_end(), "move ")?;
+            let (amount, s) = line.split_once(s, " from ")?;
+            let (from, to) = line.split_once(s, " to ")?;
+            let mv = Move {
+                amount: line.parse(amount)?,
+                from: line.parse(from)?,
+                to: line.parse(to)?,
+            };
+            if !stacks.contains_key(&mv.from) {
+                return Err(line.error(from, "unknown stack"));
+            }
+            if !stacks.contains_key(&mv.to) {
+                return Err(line.error(to, "unknown stack"));
+            }
+            Ok(mv)
         })
-        .collect();
-    Input { stacks, moves }
+        .collect::<Result<_, _>>()?;
+    Ok(Input { stacks, moves })
 }
 
 impl Move {
@@ -124,13 +148,13 @@ move 1 from 1 to 2
 
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(&part1(&input), "CMZ");
     }
 
     #[test]
     fn test_part2() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(&part2(&input), "MCD");
     }
 }
@@ -1,6 +1,16 @@
+use crate::util::{Line, ParseError};
+
 #[aoc_generator(day6)]
-pub fn input_generator(input: &str) -> String {
-    input.to_string()
+pub fn input_generator(input: &str) -> Result<String, ParseError> {
+    let line = Line::new(6, 1, input.trim_end());
+    if let Some((x, _)) = line
+        .text
+        .char_indices()
+        .find(|(_, c)| !c.is_ascii_lowercase())
+    {
+        return Err(line.error(&line.text[x..], "invalid character"));
+    }
+    Ok(line.text.to_string())
 }
 
 const PACKET_MARKER_LEN: usize = 4;
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 use std::str::FromStr;
 
+use crate::util::{lines, Line, ParseError};
+
 #[derive(Debug)]
 pub enum Command {
     CD(String),
@@ -14,12 +16,14 @@ pub struct Directory {
 }
 
 #[aoc_generator(day7)]
-pub fn input_generator(input: &str) -> String {
-    input.to_string()
+pub fn input_generator(input: &str) -> Result<Directory, ParseError> {
+    let mut fs = FileSystem::default();
+    fs.process_commands(input)?;
+    Ok(fs.root)
 }
 
 impl FromStr for Command {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if let Some(dir) = s.strip_prefix("$ cd ") {
@@ -27,7 +31,7 @@ impl FromStr for Command {
         } else if s == "$ ls" {
             Ok(Command::LS)
         } else {
-            panic!("unknown command: {}", s)
+            Err(Line::new(7, 1, s).error(s, "unknown command"))
         }
     }
 }
@@ -39,7 +43,7 @@ struct FileSystem {
 }
 
 impl FileSystem {
-    fn change_directory(&mut self, path: &str) {
+    fn change_directory(&mut self, path: &str) -> Result<(), &'static str> {
         match path {
             "/" => {
                 self.current_directory.clear();
@@ -47,23 +51,25 @@ impl FileSystem {
             ".." => {
                 self.current_directory
                     .pop()
-                    .expect("no parent directory found");
+                    .ok_or("no parent directory found")?;
             }
             path => {
                 self.current_directory.push(path.to_string());
+                if self.current_directory_mut().is_none() {
+                    self.current_directory.pop();
+                    return Err("directory not found");
+                }
             }
         }
+        Ok(())
     }
 
-    fn current_directory_mut(&mut self) -> &mut Directory {
+    fn current_directory_mut(&mut self) -> Option<&mut Directory> {
         let mut directory = &mut self.root;
         for part in &self.current_directory {
-            directory = directory
-                .directories
-                .get_mut(part)
-                .expect("directory not found");
+            directory = directory.directories.get_mut(part)?;
         }
-        directory
+        Some(directory)
     }
 }
 
@@ -86,41 +92,41 @@ impl Directory {
 }
 
 impl FileSystem {
-    fn process_commands(&mut self, input: &str) {
-        let mut lines = input.lines().into_iter().peekable();
-        while let Some(command) = lines.next() {
-            match command.parse().unwrap() {
+    fn process_commands(&mut self, input: &str) -> Result<(), ParseError> {
+        let mut lines = lines(7, input).peekable();
+        while let Some(line) = lines.next() {
+            match line.parse_line()? {
                 Command::CD(dir) => {
-                    self.change_directory(&dir);
+                    self.change_directory(&dir)
+                        .map_err(|err| line.error(&line.text[5..], err))?;
                 }
                 Command::LS => {
-                    let current_directory = self.current_directory_mut();
-                    while let Some(line) = lines.next_if(|s| !s.starts_with('$')) {
-                        if let Some(dir) = line.strip_prefix("dir ") {
+                    let current_directory = self.current_directory_mut().unwrap();
+                    while let Some(line) = lines.next_if(|line| !line.text.starts_with('$')) {
+                        if let Some(dir) = line.text.strip_prefix("dir ") {
                             current_directory
                                 .directories
                                 .entry(dir.to_string())
                                 .or_default();
-                        } else if let Some((size, name)) = line.split_once(' ') {
+                        } else if let Some((size, name)) = line.text.split_once(' ') {
                             current_directory
                                 .files
-                                .push((name.to_string(), size.parse().unwrap()));
+                                .push((name.to_string(), line.parse(size)?));
                         } else {
-                            panic!("unknown listing: {}", line);
+                            return Err(line.error(line.text, "unknown listing"));
                         }
                     }
                 }
             }
         }
+        Ok(())
     }
 }
 
 #[aoc(day7, part1)]
-pub fn part1(input: &str) -> u64 {
-    let mut fs = FileSystem::default();
-    fs.process_commands(input);
+pub fn part1(input: &Directory) -> u64 {
     let mut total_size = 0u64;
-    fs.root.visit(&mut |dir| {
+    input.visit(&mut |dir| {
         let dir_size = dir.total_size();
         if dir_size <= 100_000 {
             total_size += dir_size;
@@ -130,16 +136,13 @@ pub fn part1(input: &str) -> u64 {
 }
 
 #[aoc(day7, part2)]
-pub fn part2(input: &str) -> u64 {
-    let mut fs = FileSystem::default();
-    fs.process_commands(input);
-
+pub fn part2(input: &Directory) -> u64 {
     let total_space = 70_000_000u64;
-    let unused_space = total_space - fs.root.total_size();
+    let unused_space = total_space - input.total_size();
     let needed_unused_space = 30_000_000u64;
 
     let mut smallest_candidate: Option<u64> = None;
-    fs.root.visit(&mut |dir| {
+    input.visit(&mut |dir| {
         let dir_size = dir.total_size();
         if unused_space + dir_size >= needed_unused_space {
             smallest_candidate = Some(match smallest_candidate {
@@ -186,13 +189,13 @@ $ ls
 
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part1(&input), 95437);
     }
 
     #[test]
     fn test_part2() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part2(&input), 24933642);
     }
 }
@@ -1,38 +1,21 @@
-use std::collections::HashMap;
-
-use crate::util::Vector2D;
+use crate::util::{Grid, ParseError, Vector2D};
 
 #[derive(Debug)]
 pub struct Forest {
-    width: i32,
-    height: i32,
-    trees: HashMap<Vector2D, u32>,
+    trees: Grid<u32>,
 }
 
 #[aoc_generator(day8)]
-pub fn input_generator(input: &str) -> Forest {
-    let height = input.lines().count() as i32;
-    let width = input.lines().next().unwrap().len() as i32;
-    let trees = input
-        .lines()
-        .enumerate()
-        .flat_map(|(y, line)| {
-            line.char_indices()
-                .map(move |(x, c)| (Vector2D::new(x as i32, y as i32), c.to_digit(10).unwrap()))
-        })
-        .collect();
-    Forest {
-        width,
-        height,
-        trees,
-    }
+pub fn input_generator(input: &str) -> Result<Forest, ParseError> {
+    let trees = Grid::parse(8, input, |c| c.to_digit(10))?;
+    Ok(Forest { trees })
 }
 
 impl Forest {
     fn count_visible(&self) -> usize {
         self.trees
-            .keys()
-            .filter(|&&pos| self.is_visible(pos))
+            .positions()
+            .filter(|&pos| self.is_visible(pos))
             .count()
     }
 
@@ -44,10 +27,9 @@ impl Forest {
     }
 
     fn is_visible_in_direction(&self, tree_pos: Vector2D, dir: Vector2D) -> bool {
-        let tree = *self.trees.get(&tree_pos).unwrap();
+        let tree = self.trees[tree_pos];
         let mut pos = tree_pos + dir;
-        while (0..self.width).contains(&pos.x()) && (0..self.height).contains(&pos.y()) {
-            let other_tree = *self.trees.get(&pos).unwrap();
+        while let Some(&other_tree) = self.trees.get(pos) {
             if other_tree >= tree {
                 return false;
             }
@@ -71,12 +53,11 @@ impl Forest {
     }
 
     fn viewing_distance(&self, tree_pos: Vector2D, dir: Vector2D) -> u64 {
-        let tree = *self.trees.get(&tree_pos).unwrap();
+        let tree = self.trees[tree_pos];
         let mut viewing_distance = 0;
         let mut pos = tree_pos + dir;
-        while (0..self.width).contains(&pos.x()) && (0..self.height).contains(&pos.y()) {
+        while let Some(&other_tree) = self.trees.get(pos) {
             viewing_distance += 1;
-            let other_tree = *self.trees.get(&pos).unwrap();
             if other_tree >= tree {
                 break;
             }
@@ -90,8 +71,8 @@ impl Forest {
 pub fn part2(input: &Forest) -> u64 {
     input
         .trees
-        .keys()
-        .map(|&pos| input.scenic_score(pos))
+        .positions()
+        .map(|pos| input.scenic_score(pos))
         .max()
         .unwrap()
 }
@@ -112,13 +93,13 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part1(&input), 21);
     }
 
     #[test]
     fn test_part2() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part2(&input), 8);
     }
 }
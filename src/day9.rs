@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 
-use crate::util::Vector2D;
+use crate::util::{lines, ParseError, Vector2D};
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Direction {
@@ -13,20 +13,19 @@ pub enum Direction {
 pub type Move = (Direction, i32);
 
 #[aoc_generator(day9)]
-pub fn input_generator(input: &str) -> Vec<Move> {
-    input
-        .lines()
+pub fn input_generator(input: &str) -> Result<Vec<Move>, ParseError> {
+    lines(9, input)
         .map(|line| {
-            let (dir, steps) = line.split_once(' ').unwrap();
+            let (dir, steps) = line.split_once(line.text, " ")?;
             let dir = match dir {
                 "U" => Direction::Up,
                 "D" => Direction::Down,
                 "L" => Direction::Left,
                 "R" => Direction::Right,
-                _ => panic!("invalid direction: {}", dir),
+                _ => return Err(line.error(dir, "invalid direction")),
             };
-            let steps = steps.parse().unwrap();
-            (dir, steps)
+            let steps = line.parse(steps)?;
+            Ok((dir, steps))
         })
         .collect()
 }
@@ -70,20 +69,11 @@ impl<const N: usize> Rope<N> {
 
     fn update_knot(knot: Vector2D, prev_knot: Vector2D) -> Vector2D {
         let diff = prev_knot - knot;
-        if diff.x() == 0 && diff.y().abs() <= 1 {
-            // Overlapping or touching vertically
+        if diff.chebyshev_distance() <= 1 {
+            // Overlapping or touching, possibly diagonally
             return knot;
         }
-        if diff.y() == 0 && diff.x().abs() <= 1 {
-            // Touching horizontally
-            return knot;
-        }
-        let distance = diff.manhattan_distance();
-        if diff.x() != 0 && diff.y() != 0 && distance == 2 {
-            // Touching diagonally
-            return knot;
-        }
-        knot + Vector2D::new(diff.x().signum(), diff.y().signum())
+        knot + diff.signum()
     }
 }
 
@@ -140,19 +130,19 @@ U 20"
 
     #[test]
     fn test_part1() {
-        let input = input_generator(&INPUT1);
+        let input = input_generator(&INPUT1).unwrap();
         assert_eq!(part1(&input), 13);
     }
 
     #[test]
     fn test_part2_input1() {
-        let input = input_generator(&INPUT1);
+        let input = input_generator(&INPUT1).unwrap();
         assert_eq!(part2(&input), 1);
     }
 
     #[test]
     fn test_part2_input2() {
-        let input = input_generator(&INPUT2);
+        let input = input_generator(&INPUT2).unwrap();
         assert_eq!(part2(&input), 36);
     }
 }
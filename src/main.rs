@@ -1,7 +1,396 @@
 extern crate advent_of_code_2022;
-extern crate aoc_runner;
-extern crate aoc_runner_derive;
 
-use aoc_runner_derive::aoc_main;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::Read;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+use std::{env, fs, io, thread};
 
-aoc_main! { lib = advent_of_code_2022 }
+const YEAR: u32 = 2022;
+
+const USAGE: &str = "\
+Usage: advent-of-code-2022 [verify] [OPTIONS]
+
+Runs the solutions for every day, or only for the selected days and parts.
+With `verify`, checks the answers for the puzzle inputs against `answers/`.
+
+Options:
+  -d, --day <DAY>      Only run the given day (can be repeated)
+  -p, --part <PART>    Only run the given part
+  -i, --input <PATH>   Read the input from PATH, or from stdin if PATH is `-`
+                       (requires a single --day)
+  -e, --example        Use the example inputs in `examples/` instead of `input/`
+  -c, --concurrent     Run all selected days at the same time, each on its own thread
+                       (timings will be less accurate)
+  -h, --help           Print this help";
+
+/// Runs the generator and the given parts on an input, and records the results in a report.
+pub type DayRunner = fn(&str, &[u32], &mut DayReport) -> Result<(), Box<dyn Error>>;
+
+/// A day's solutions, as found by `build.rs`.
+pub struct Day {
+    pub day: u32,
+    pub parts: &'static [u32],
+    pub run: DayRunner,
+}
+
+/// The results of running (some parts of) a single day.
+#[derive(Debug, Default)]
+pub struct DayReport {
+    pub generator: Option<Duration>,
+    pub parts: Vec<PartReport>,
+}
+
+#[derive(Debug)]
+pub struct PartReport {
+    pub part: u32,
+    pub answer: String,
+    pub elapsed: Duration,
+}
+
+mod registry {
+    use super::*;
+
+    include!(concat!(env!("OUT_DIR"), "/registry.rs"));
+}
+
+#[derive(Debug, Default)]
+struct Options {
+    /// Only print the usage, and ignore everything else.
+    help: bool,
+    verify: bool,
+    days: Vec<u32>,
+    part: Option<u32>,
+    input: Option<PathBuf>,
+    example: bool,
+    concurrent: bool,
+}
+
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("missing value for {}", name))
+        };
+        match arg.as_str() {
+            "-d" | "--day" => {
+                let day = value("--day")?;
+                let day = day.parse().map_err(|_| format!("invalid day `{}`", day))?;
+                options.days.push(day);
+            }
+            "-p" | "--part" => {
+                let part = value("--part")?;
+                let part = part
+                    .parse()
+                    .map_err(|_| format!("invalid part `{}`", part))?;
+                options.part = Some(part);
+            }
+            "-i" | "--input" => options.input = Some(PathBuf::from(value("--input")?)),
+            "-e" | "--example" => options.example = true,
+            "-c" | "--concurrent" => options.concurrent = true,
+            "verify" => options.verify = true,
+            "-h" | "--help" => {
+                options.help = true;
+                return Ok(options);
+            }
+            _ => return Err(format!("unexpected argument `{}`\n\n{}", arg, USAGE)),
+        }
+    }
+    if options.verify && (options.input.is_some() || options.example) {
+        return Err("verify only works with the puzzle inputs".to_string());
+    }
+    if options.input.is_some() && options.days.len() != 1 {
+        return Err("--input requires exactly one --day".to_string());
+    }
+    for &day in &options.days {
+        if !registry::DAYS.iter().any(|d| d.day == day) {
+            return Err(format!("no solutions for day {}", day));
+        }
+    }
+    Ok(options)
+}
+
+fn read_input(options: &Options, day: u32) -> io::Result<String> {
+    let input = match &options.input {
+        Some(path) if path == Path::new("-") => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            input
+        }
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            let dir = if options.example { "examples" } else { "input" };
+            let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join(dir)
+                .join(YEAR.to_string())
+                .join(format!("day{}.txt", day));
+            fs::read_to_string(&path)
+                .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))?
+        }
+    };
+    Ok(input.trim_end_matches('\n').to_string())
+}
+
+/// A day to run, with the selected parts and its input.
+struct Job {
+    day: &'static Day,
+    parts: Vec<u32>,
+    input: Result<String, String>,
+}
+
+fn select_jobs(options: &Options) -> Vec<Job> {
+    let mut jobs = Vec::new();
+    for day in registry::DAYS {
+        if !options.days.is_empty() && !options.days.contains(&day.day) {
+            continue;
+        }
+        let parts = match options.part {
+            Some(part) if day.parts.contains(&part) => vec![part],
+            Some(_) => continue,
+            None => day.parts.to_vec(),
+        };
+        let input = match read_input(options, day.day) {
+            Ok(input) => Ok(input),
+            Err(err) if options.example && err.kind() == io::ErrorKind::NotFound => {
+                // Not every day has an example input.
+                if options.days.is_empty() {
+                    continue;
+                }
+                Err(format!("no example input: {}", err))
+            }
+            Err(err) => Err(format!("cannot read input: {}", err)),
+        };
+        jobs.push(Job { day, parts, input });
+    }
+    jobs
+}
+
+/// Runs a single day, catching any errors or panics.
+fn run_day(job: &Job) -> (DayReport, Result<(), String>) {
+    let mut report = DayReport::default();
+    let input = match &job.input {
+        Ok(input) => input,
+        Err(err) => return (report, Err(err.clone())),
+    };
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        (job.day.run)(input, &job.parts, &mut report)
+    }));
+    let result = match result {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(err)) => Err(err.to_string()),
+        Err(_) => Err("panicked".to_string()),
+    };
+    (report, result)
+}
+
+/// Runs all jobs, and calls `done` with their results in order.
+fn run_jobs(
+    jobs: &[Job],
+    concurrent: bool,
+    mut done: impl FnMut(&Job, DayReport, Result<(), String>),
+) {
+    if !concurrent {
+        for job in jobs {
+            let (report, result) = run_day(job);
+            done(job, report, result);
+        }
+        return;
+    }
+    thread::scope(|scope| {
+        let handles = jobs
+            .iter()
+            .map(|job| {
+                thread::Builder::new()
+                    .name(format!("day{}", job.day.day))
+                    // Same as the main thread, for the more recursive solutions.
+                    .stack_size(8 * 1024 * 1024)
+                    .spawn_scoped(scope, move || run_day(job))
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        for (job, handle) in jobs.iter().zip(handles) {
+            // `run_day` catches panics, so joining cannot fail.
+            let (report, result) = handle.join().unwrap();
+            done(job, report, result);
+        }
+    });
+}
+
+fn print_report(day: u32, report: &DayReport) {
+    if let Some(elapsed) = report.generator {
+        println!("Day {} - Generator: {:?}", day, elapsed);
+    }
+    for part in &report.parts {
+        println!("Day {} - Part {}: {}", day, part.part, part.answer);
+        println!("\trunner: {:?}", part.elapsed);
+    }
+}
+
+/// The known answers, indexed by day and part.
+type Answers = HashMap<(u32, u32), String>;
+
+fn read_answers() -> Result<Answers, String> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("answers")
+        .join(format!("{}.toml", YEAR));
+    let text = fs::read_to_string(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    let table = text
+        .parse::<toml::Value>()
+        .map_err(|err| format!("{}: {}", path.display(), err))?;
+    let mut answers = Answers::new();
+    for (day_key, parts) in table.as_table().into_iter().flatten() {
+        let day = day_key.strip_prefix("day").and_then(|day| day.parse().ok());
+        let parts = parts.as_table();
+        let (Some(day), Some(parts)) = (day, parts) else {
+            return Err(format!("{}: invalid section `{}`", path.display(), day_key));
+        };
+        for (part_key, answer) in parts {
+            let part = part_key
+                .strip_prefix("part")
+                .and_then(|part| part.parse().ok());
+            let answer = match answer {
+                toml::Value::String(answer) => Some(answer.clone()),
+                toml::Value::Integer(answer) => Some(answer.to_string()),
+                _ => None,
+            };
+            let (Some(part), Some(answer)) = (part, answer) else {
+                return Err(format!(
+                    "{}: invalid answer `{}.{}`",
+                    path.display(),
+                    day_key,
+                    part_key
+                ));
+            };
+            answers.insert((day, part), answer);
+        }
+    }
+    Ok(answers)
+}
+
+fn truncate(answer: &str) -> String {
+    let mut lines = answer.lines();
+    let first = lines.next().unwrap_or_default();
+    if lines.next().is_some() {
+        format!("{}…", first)
+    } else {
+        first.to_string()
+    }
+}
+
+fn verify(options: &Options) -> ExitCode {
+    let answers = match read_answers() {
+        Ok(answers) => answers,
+        Err(err) => {
+            eprintln!("cannot read answers: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    println!("Day  Part  Status   Time          Answer");
+    let mut failures = Vec::new();
+    let mut passed = 0;
+    run_jobs(
+        &select_jobs(options),
+        options.concurrent,
+        |job, report, result| {
+            let day = job.day.day;
+            for &part in &job.parts {
+                let expected = answers.get(&(day, part));
+                let Some(actual) = report.parts.iter().find(|report| report.part == part) else {
+                    println!("{:>3}  {:>4}  {:<7}", day, part, "ERROR");
+                    let err = result
+                        .as_ref()
+                        .err()
+                        .map_or("no answer", |err| err.as_str());
+                    failures.push(format!("Day {} - Part {}: {}", day, part, err));
+                    continue;
+                };
+                let status = match expected {
+                    None => "MISSING",
+                    Some(expected) if expected.trim_end() == actual.answer.trim_end() => "PASS",
+                    Some(_) => "FAIL",
+                };
+                println!(
+                    "{:>3}  {:>4}  {:<7}  {:<12}  {}",
+                    day,
+                    part,
+                    status,
+                    format!("{:?}", actual.elapsed),
+                    truncate(&actual.answer)
+                );
+                match expected {
+                    _ if status == "PASS" => passed += 1,
+                    None => failures.push(format!(
+                        "Day {} - Part {}: no known answer, got:\n{}",
+                        day,
+                        part,
+                        actual.answer.trim_end()
+                    )),
+                    Some(expected) => failures.push(format!(
+                        "Day {} - Part {}: expected:\n{}\ngot:\n{}",
+                        day,
+                        part,
+                        expected.trim_end(),
+                        actual.answer.trim_end()
+                    )),
+                }
+            }
+        },
+    );
+
+    println!();
+    println!("{} passed, {} failed", passed, failures.len());
+    for failure in &failures {
+        println!();
+        println!("{}", failure);
+    }
+    if failures.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn main() -> ExitCode {
+    let options = match parse_options(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if options.help {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    if options.verify {
+        return verify(&options);
+    }
+
+    println!("Advent of code {}", YEAR);
+    let start = Instant::now();
+    let mut failed = false;
+    run_jobs(
+        &select_jobs(&options),
+        options.concurrent,
+        |job, report, result| {
+            print_report(job.day.day, &report);
+            if let Err(err) = result {
+                eprintln!("Day {}: FAILED: {}", job.day.day, err);
+                failed = true;
+            }
+        },
+    );
+    println!("Total: {:?}", start.elapsed());
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -1,6 +1,8 @@
+use crate::util::{lines, ParseError};
+
 #[aoc_generator(day1)]
-pub fn input_generator(input: &str) -> Vec<i32> {
-    input.lines().map(|line| line.parse().unwrap()).collect()
+pub fn input_generator(input: &str) -> Result<Vec<i32>, ParseError> {
+    lines(1, input).map(|line| line.parse(line.text)).collect()
 }
 
 #[aoc(day1, part1)]
@@ -23,13 +25,13 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part1(&input), 0);
     }
 
     #[test]
     fn test_part2() {
-        let input = input_generator(&TEST_INPUT);
+        let input = input_generator(&TEST_INPUT).unwrap();
         assert_eq!(part2(&input), 0);
     }
 }
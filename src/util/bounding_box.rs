@@ -0,0 +1,157 @@
+use crate::util::{Scalar, Vector};
+
+/// An axis-aligned box of lattice points, with inclusive bounds on both ends.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct BoundingBox<T, const N: usize> {
+    pub min: Vector<T, N>,
+    pub max: Vector<T, N>,
+}
+
+impl<T: Scalar, const N: usize> BoundingBox<T, N> {
+    pub fn new(min: Vector<T, N>, max: Vector<T, N>) -> Self {
+        Self { min, max }
+    }
+
+    /// A box containing a single point.
+    pub fn from_point(point: Vector<T, N>) -> Self {
+        Self::new(point, point)
+    }
+
+    /// The smallest box containing all the given points, or `None` if there are none.
+    pub fn from_points(points: impl IntoIterator<Item = Vector<T, N>>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let mut bounds = Self::from_point(points.next()?);
+        for point in points {
+            bounds.extend(point);
+        }
+        Some(bounds)
+    }
+
+    /// Grows the box so that it contains the given point.
+    pub fn extend(&mut self, point: Vector<T, N>) {
+        self.min = self.min.min(&point);
+        self.max = self.max.max(&point);
+    }
+
+    /// Grows the box by `margin` on every side.
+    pub fn grow(self, margin: T) -> Self {
+        Self::new(self.min.map(|&x| x - margin), self.max.map(|&x| x + margin))
+    }
+
+    pub fn contains(&self, point: &Vector<T, N>) -> bool {
+        (0..N)
+            .all(|i| self.min.coords[i] <= point.coords[i] && point.coords[i] <= self.max.coords[i])
+    }
+
+    /// The number of points along each axis.
+    pub fn size(&self) -> Vector<T, N> {
+        self.max
+            .zip_with(&self.min, |&max, &min| max - min + T::ONE)
+    }
+
+    /// The number of points inside the box.
+    pub fn volume(&self) -> T {
+        self.size()
+            .coords
+            .iter()
+            .fold(T::ONE, |volume, &x| volume * x)
+    }
+
+    /// Iterates over every point in the box, with the first coordinate changing fastest.
+    pub fn iter(&self) -> BoundingBoxIter<T, N> {
+        BoundingBoxIter {
+            bounds: *self,
+            next: (0..N)
+                .all(|i| self.min.coords[i] <= self.max.coords[i])
+                .then_some(self.min),
+        }
+    }
+}
+
+impl<T: Scalar, const N: usize> IntoIterator for BoundingBox<T, N> {
+    type Item = Vector<T, N>;
+    type IntoIter = BoundingBoxIter<T, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Scalar, const N: usize> IntoIterator for &BoundingBox<T, N> {
+    type Item = Vector<T, N>;
+    type IntoIter = BoundingBoxIter<T, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct BoundingBoxIter<T, const N: usize> {
+    bounds: BoundingBox<T, N>,
+    next: Option<Vector<T, N>>,
+}
+
+impl<T: Scalar, const N: usize> Iterator for BoundingBoxIter<T, N> {
+    type Item = Vector<T, N>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next?;
+        let mut next = current;
+        self.next = None;
+        for i in 0..N {
+            if next.coords[i] < self.bounds.max.coords[i] {
+                next.coords[i] += T::ONE;
+                self.next = Some(next);
+                break;
+            }
+            next.coords[i] = self.bounds.min.coords[i];
+        }
+        Some(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{Vector2D, Vector3D};
+
+    #[test]
+    fn test_from_points() {
+        let bounds = BoundingBox::from_points([
+            Vector2D::new(2, -1),
+            Vector2D::new(-3, 4),
+            Vector2D::new(0, 0),
+        ])
+        .unwrap();
+        assert_eq!(bounds.min, Vector2D::new(-3, -1));
+        assert_eq!(bounds.max, Vector2D::new(2, 4));
+        assert_eq!(bounds.size(), Vector2D::new(6, 6));
+        assert_eq!(bounds.volume(), 36);
+        assert!(bounds.contains(&Vector2D::new(-3, 4)));
+        assert!(!bounds.contains(&Vector2D::new(3, 0)));
+        assert_eq!(BoundingBox::<i32, 2>::from_points([]), None);
+    }
+
+    #[test]
+    fn test_iter() {
+        let bounds = BoundingBox::new(Vector2D::new(0, 0), Vector2D::new(1, 2));
+        assert_eq!(
+            bounds.iter().collect::<Vec<_>>(),
+            vec![
+                Vector2D::new(0, 0),
+                Vector2D::new(1, 0),
+                Vector2D::new(0, 1),
+                Vector2D::new(1, 1),
+                Vector2D::new(0, 2),
+                Vector2D::new(1, 2),
+            ]
+        );
+
+        let bounds = BoundingBox::from_point(Vector3D::new(1, 1, 1)).grow(1);
+        assert_eq!(bounds.iter().count() as i32, bounds.volume());
+        assert!(bounds.iter().all(|point| bounds.contains(&point)));
+
+        let empty = BoundingBox::new(Vector2D::new(1, 0), Vector2D::new(0, 0));
+        assert_eq!(empty.iter().count(), 0);
+    }
+}
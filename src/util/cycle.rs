@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// A cycle in a sequence of states `x0, step(x0), step(step(x0)), ...`,
+/// together with a measured value for each state up to the end of the first cycle.
+///
+/// Assumes the measured value grows by the same `delta` every time the cycle repeats,
+/// which is what makes it possible to extrapolate it arbitrarily far ahead.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Cycle {
+    /// The number of steps before the cycle starts.
+    pub prefix: usize,
+    /// The number of steps in one repetition of the cycle.
+    pub length: usize,
+    /// How much the measured value changes over one repetition of the cycle.
+    pub delta: i64,
+    /// The measured values of steps `0..=prefix + length`.
+    values: Vec<i64>,
+}
+
+impl Cycle {
+    fn new(prefix: usize, length: usize, values: Vec<i64>) -> Self {
+        debug_assert_eq!(values.len(), prefix + length + 1);
+        let delta = values[prefix + length] - values[prefix];
+        Self {
+            prefix,
+            length,
+            delta,
+            values,
+        }
+    }
+
+    /// Finds the cycle using Floyd's tortoise and hare algorithm.
+    ///
+    /// Two states with the same key are assumed to have the same future, except for
+    /// their measured values. Only keeps a constant number of states in memory, but steps
+    /// through the sequence several times. Never returns if the sequence doesn't cycle.
+    #[allow(dead_code)]
+    pub fn floyd<S: Clone, K: Eq>(
+        initial: &S,
+        step: impl Fn(&mut S),
+        key: impl Fn(&S) -> K,
+        measure: impl Fn(&S) -> i64,
+    ) -> Self {
+        let mut tortoise = initial.clone();
+        let mut hare = initial.clone();
+        loop {
+            step(&mut tortoise);
+            step(&mut hare);
+            step(&mut hare);
+            if key(&tortoise) == key(&hare) {
+                break;
+            }
+        }
+        // The distance between them is now a multiple of the cycle length,
+        // so they meet again exactly at the start of the cycle.
+        let mut prefix = 0;
+        tortoise = initial.clone();
+        while key(&tortoise) != key(&hare) {
+            step(&mut tortoise);
+            step(&mut hare);
+            prefix += 1;
+        }
+        let mut length = 1;
+        step(&mut hare);
+        while key(&tortoise) != key(&hare) {
+            step(&mut hare);
+            length += 1;
+        }
+        Self::new(
+            prefix,
+            length,
+            measure_all(initial, step, measure, prefix + length),
+        )
+    }
+
+    /// Finds the cycle using Brent's algorithm.
+    ///
+    /// Like [`Cycle::floyd`], but usually takes fewer steps.
+    #[allow(dead_code)]
+    pub fn brent<S: Clone, K: Eq>(
+        initial: &S,
+        step: impl Fn(&mut S),
+        key: impl Fn(&S) -> K,
+        measure: impl Fn(&S) -> i64,
+    ) -> Self {
+        let mut power = 1;
+        let mut length = 1;
+        let mut tortoise = key(initial);
+        let mut hare = initial.clone();
+        step(&mut hare);
+        while tortoise != key(&hare) {
+            if power == length {
+                tortoise = key(&hare);
+                power *= 2;
+                length = 0;
+            }
+            step(&mut hare);
+            length += 1;
+        }
+        // Start the hare one cycle ahead of the tortoise, then find where they meet.
+        let mut prefix = 0;
+        let mut tortoise = initial.clone();
+        hare = initial.clone();
+        for _ in 0..length {
+            step(&mut hare);
+        }
+        while key(&tortoise) != key(&hare) {
+            step(&mut tortoise);
+            step(&mut hare);
+            prefix += 1;
+        }
+        Self::new(
+            prefix,
+            length,
+            measure_all(initial, step, measure, prefix + length),
+        )
+    }
+
+    /// Finds the cycle by remembering the key of every state that was seen.
+    ///
+    /// Two states with the same key are assumed to have the same future, except for
+    /// their measured values. Use this when the state itself is too big to remember.
+    pub fn find_by_key<S, K: Eq + Hash>(
+        mut state: S,
+        mut step: impl FnMut(&mut S),
+        key: impl Fn(&S) -> K,
+        measure: impl Fn(&S) -> i64,
+    ) -> Self {
+        let mut seen = HashMap::<K, usize>::new();
+        let mut values = Vec::new();
+        loop {
+            let i = values.len();
+            values.push(measure(&state));
+            if let Some(previous) = seen.insert(key(&state), i) {
+                return Self::new(previous, i - previous, values);
+            }
+            step(&mut state);
+        }
+    }
+
+    /// The measured value after `steps` steps.
+    pub fn extrapolate(&self, steps: u64) -> i64 {
+        if let Some(&value) = self.values.get(steps as usize) {
+            return value;
+        }
+        let after_prefix = steps - self.prefix as u64;
+        let cycles = after_prefix / self.length as u64;
+        let remainder = (after_prefix % self.length as u64) as usize;
+        self.values[self.prefix + remainder] + (cycles as i64) * self.delta
+    }
+}
+
+/// Measures the first `steps + 1` states of the sequence.
+#[allow(dead_code)]
+fn measure_all<S: Clone>(
+    initial: &S,
+    step: impl Fn(&mut S),
+    measure: impl Fn(&S) -> i64,
+    steps: usize,
+) -> Vec<i64> {
+    let mut state = initial.clone();
+    let mut values = vec![measure(&state)];
+    for _ in 0..steps {
+        step(&mut state);
+        values.push(measure(&state));
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts up to 10, then wraps around to 4.
+    /// The measured value is the sum of all states so far.
+    fn step(state: &mut (u32, i64)) {
+        state.0 = if state.0 == 10 { 4 } else { state.0 + 1 };
+        state.1 += state.0 as i64;
+    }
+
+    fn brute_force(steps: u64) -> i64 {
+        let mut state = (0, 0);
+        for _ in 0..steps {
+            step(&mut state);
+        }
+        state.1
+    }
+
+    #[test]
+    fn test_find_by_key() {
+        let cycle = Cycle::find_by_key((0, 0), step, |state| state.0, |state| state.1);
+        assert_eq!(cycle.prefix, 4);
+        assert_eq!(cycle.length, 7);
+        assert_eq!(cycle.delta, 4 + 5 + 6 + 7 + 8 + 9 + 10);
+        for steps in 0..50 {
+            assert_eq!(cycle.extrapolate(steps), brute_force(steps));
+        }
+    }
+
+    #[test]
+    fn test_floyd_and_brent() {
+        let expected = Cycle::find_by_key((0, 0), step, |state| state.0, |state| state.1);
+        for cycle in [
+            Cycle::floyd(&(0, 0), step, |state| state.0, |state| state.1),
+            Cycle::brent(&(0, 0), step, |state| state.0, |state| state.1),
+        ] {
+            assert_eq!(cycle, expected);
+            for steps in 0..50 {
+                assert_eq!(cycle.extrapolate(steps), brute_force(steps));
+            }
+        }
+    }
+
+    #[test]
+    fn test_cycle_from_start() {
+        let cycle = Cycle::brent(
+            &0,
+            |state: &mut i64| *state = (*state + 1) % 3,
+            |&state| state,
+            |&state| state,
+        );
+        assert_eq!((cycle.prefix, cycle.length), (0, 3));
+        assert_eq!(cycle.extrapolate(10), 1);
+    }
+}
@@ -0,0 +1,527 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::ops::{Index, IndexMut};
+
+use crate::util::{lines, Line, ParseError, Vector2D};
+
+/// A dense 2D grid, covering all positions from `min()` up to and including `max()`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Grid<T> {
+    origin: Vector2D,
+    width: i32,
+    height: i32,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    pub fn new(width: i32, height: i32, value: T) -> Self
+    where
+        T: Clone,
+    {
+        Self::with_bounds(
+            Vector2D::zero(),
+            Vector2D::new(width - 1, height - 1),
+            value,
+        )
+    }
+
+    /// Creates a grid covering all positions between `min` and `max` (inclusive).
+    pub fn with_bounds(min: Vector2D, max: Vector2D, value: T) -> Self
+    where
+        T: Clone,
+    {
+        let size = max - min + Vector2D::new(1, 1);
+        let (width, height) = (size.x().max(0), size.y().max(0));
+        Self {
+            origin: min,
+            width,
+            height,
+            cells: vec![value; (width * height) as usize],
+        }
+    }
+
+    pub fn from_fn(width: i32, height: i32, mut f: impl FnMut(Vector2D) -> T) -> Self {
+        let cells = (0..height)
+            .flat_map(|y| (0..width).map(move |x| Vector2D::new(x, y)))
+            .map(&mut f)
+            .collect();
+        Self {
+            origin: Vector2D::zero(),
+            width,
+            height,
+            cells,
+        }
+    }
+
+    /// Parses a grid from a character map, converting each character with `f`.
+    ///
+    /// Lines shorter than the longest line are padded with spaces.
+    /// If `f` returns `None`, parsing fails with an error pointing at that character.
+    pub fn parse(
+        day: u32,
+        input: &str,
+        f: impl FnMut(char) -> Option<T>,
+    ) -> Result<Self, ParseError> {
+        Self::from_lines(&lines(day, input).collect::<Vec<_>>(), f)
+    }
+
+    /// Like `parse()`, but for a set of lines taken from a larger input.
+    pub fn from_lines(
+        lines: &[Line],
+        mut f: impl FnMut(char) -> Option<T>,
+    ) -> Result<Self, ParseError> {
+        let width = lines
+            .iter()
+            .map(|line| line.text.chars().count())
+            .max()
+            .unwrap_or_default();
+        let mut cells = Vec::with_capacity(width * lines.len());
+        for line in lines {
+            let mut count = 0;
+            for (x, c) in line.text.char_indices() {
+                let cell = f(c).ok_or_else(|| {
+                    line.error(&line.text[x..], format!("unexpected character `{}`", c))
+                })?;
+                cells.push(cell);
+                count += 1;
+            }
+            for _ in count..width {
+                let cell = f(' ').ok_or_else(|| {
+                    line.error(&line.text[line.text.len()..], "line is too short")
+                })?;
+                cells.push(cell);
+            }
+        }
+        Ok(Self {
+            origin: Vector2D::zero(),
+            width: width as i32,
+            height: lines.len() as i32,
+            cells,
+        })
+    }
+
+    /// Moves the grid so its top-left corner is at the given position.
+    pub fn with_origin(self, origin: Vector2D) -> Self {
+        Self { origin, ..self }
+    }
+
+    #[inline]
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    /// The top-left corner.
+    #[inline]
+    pub fn min(&self) -> Vector2D {
+        self.origin
+    }
+
+    /// The bottom-right corner.
+    #[inline]
+    pub fn max(&self) -> Vector2D {
+        self.origin + Vector2D::new(self.width - 1, self.height - 1)
+    }
+
+    #[inline]
+    pub fn contains(&self, pos: Vector2D) -> bool {
+        self.index_of(pos).is_some()
+    }
+
+    #[inline]
+    fn index_of(&self, pos: Vector2D) -> Option<usize> {
+        let pos = pos - self.origin;
+        if (0..self.width).contains(&pos.x()) && (0..self.height).contains(&pos.y()) {
+            Some((pos.y() * self.width + pos.x()) as usize)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn pos_of(&self, index: usize) -> Vector2D {
+        let index = index as i32;
+        self.origin + Vector2D::new(index % self.width, index / self.width)
+    }
+
+    #[inline]
+    pub fn get(&self, pos: Vector2D) -> Option<&T> {
+        self.index_of(pos).map(|index| &self.cells[index])
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, pos: Vector2D) -> Option<&mut T> {
+        self.index_of(pos).map(|index| &mut self.cells[index])
+    }
+
+    /// All positions in the grid, row by row.
+    pub fn positions(&self) -> impl Iterator<Item = Vector2D> + '_ {
+        (0..self.cells.len()).map(|index| self.pos_of(index))
+    }
+
+    /// All cells in the grid along with their positions, row by row.
+    pub fn iter(&self) -> impl Iterator<Item = (Vector2D, &T)> + '_ {
+        self.cells
+            .iter()
+            .enumerate()
+            .map(|(index, cell)| (self.pos_of(index), cell))
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> + '_ {
+        self.cells.iter()
+    }
+
+    pub fn row(&self, y: i32) -> Option<&[T]> {
+        let start = self.index_of(Vector2D::new(self.origin.x(), y))?;
+        Some(&self.cells[start..start + self.width as usize])
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> + '_ {
+        self.cells.chunks_exact(self.width.max(1) as usize)
+    }
+
+    pub fn column(&self, x: i32) -> Option<impl Iterator<Item = &T> + '_> {
+        let start = self.index_of(Vector2D::new(x, self.origin.y()))?;
+        Some(self.cells[start..].iter().step_by(self.width as usize))
+    }
+
+    pub fn columns(&self) -> impl Iterator<Item = impl Iterator<Item = &T> + '_> + '_ {
+        (self.origin.x()..=self.max().x()).filter_map(|x| self.column(x))
+    }
+
+    /// The orthogonal neighbours of `pos` which lie inside the grid.
+    pub fn neighbours(&self, pos: Vector2D) -> impl Iterator<Item = (Vector2D, &T)> + '_ {
+        pos.neighbours()
+            .filter_map(|neighbour| Some((neighbour, self.get(neighbour)?)))
+    }
+
+    /// The orthogonal and diagonal neighbours of `pos` which lie inside the grid.
+    pub fn neighbours_diagonal(&self, pos: Vector2D) -> impl Iterator<Item = (Vector2D, &T)> + '_ {
+        pos.neighbours_diagonal()
+            .filter_map(|neighbour| Some((neighbour, self.get(neighbour)?)))
+    }
+
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+        Grid {
+            origin: self.origin,
+            width: self.width,
+            height: self.height,
+            cells: self.cells.iter().map(f).collect(),
+        }
+    }
+
+    /// Grows the grid so it contains `pos`, filling new cells with `value`.
+    ///
+    /// A margin of half the current size is added on every side,
+    /// so repeatedly growing the grid is amortized O(1).
+    pub fn grow_to_include(&mut self, pos: Vector2D, value: T)
+    where
+        T: Clone,
+    {
+        if self.contains(pos) {
+            return;
+        }
+        if self.cells.is_empty() {
+            *self = Grid::with_bounds(pos, pos, value);
+            return;
+        }
+        let (min, max) = (self.min(), self.max());
+        let margin = Vector2D::new(self.width / 2 + 1, self.height / 2 + 1);
+        let new_min = min.zip_with(&pos, |&a, &b| a.min(b)) - margin;
+        let new_max = max.zip_with(&pos, |&a, &b| a.max(b)) + margin;
+        let mut grown = Grid::with_bounds(new_min, new_max, value);
+        let old_cells = std::mem::take(&mut self.cells);
+        for (index, cell) in old_cells.into_iter().enumerate() {
+            let pos = self.pos_of(index);
+            grown[pos] = cell;
+        }
+        *self = grown;
+    }
+}
+
+impl<T> Index<Vector2D> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, pos: Vector2D) -> &Self::Output {
+        self.get(pos)
+            .unwrap_or_else(|| panic!("position out of bounds: {}", pos))
+    }
+}
+
+impl<T> IndexMut<Vector2D> for Grid<T> {
+    fn index_mut(&mut self, pos: Vector2D) -> &mut Self::Output {
+        self.get_mut(pos)
+            .unwrap_or_else(|| panic!("position out of bounds: {}", pos))
+    }
+}
+
+impl<T: Display> Display for Grid<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (y, row) in self.rows().enumerate() {
+            if y > 0 {
+                writeln!(f)?;
+            }
+            for cell in row {
+                write!(f, "{}", cell)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A sparse 2D grid, which only stores the cells that were inserted.
+/// Its bounds grow to include every inserted position.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SparseGrid<T> {
+    cells: HashMap<Vector2D, T>,
+    min: Vector2D,
+    max: Vector2D,
+}
+
+impl<T> SparseGrid<T> {
+    #[allow(dead_code)]
+    pub fn new() -> Self {
+        Self {
+            cells: HashMap::new(),
+            min: Vector2D::zero(),
+            max: Vector2D::new(-1, -1),
+        }
+    }
+
+    /// Parses a grid from a character map, keeping only the cells for which `f` returns a value.
+    #[allow(dead_code)]
+    pub fn parse(
+        day: u32,
+        input: &str,
+        mut f: impl FnMut(char) -> Result<Option<T>, &'static str>,
+    ) -> Result<Self, ParseError> {
+        let mut grid = Self::new();
+        for (y, line) in lines(day, input).enumerate() {
+            for (x, c) in line.text.char_indices() {
+                let pos = Vector2D::new(x as i32, y as i32);
+                match f(c) {
+                    Ok(Some(cell)) => {
+                        grid.insert(pos, cell);
+                    }
+                    Ok(None) => {}
+                    Err(message) => return Err(line.error(&line.text[x..], message)),
+                }
+            }
+        }
+        Ok(grid)
+    }
+
+    #[inline]
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    #[inline]
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// The top-left corner of the area covered by all inserted cells.
+    #[inline]
+    #[allow(dead_code)]
+    pub fn min(&self) -> Vector2D {
+        self.min
+    }
+
+    /// The bottom-right corner of the area covered by all inserted cells.
+    #[inline]
+    #[allow(dead_code)]
+    pub fn max(&self) -> Vector2D {
+        self.max
+    }
+
+    #[inline]
+    #[allow(dead_code)]
+    pub fn contains(&self, pos: Vector2D) -> bool {
+        self.cells.contains_key(&pos)
+    }
+
+    #[inline]
+    #[allow(dead_code)]
+    pub fn get(&self, pos: Vector2D) -> Option<&T> {
+        self.cells.get(&pos)
+    }
+
+    #[inline]
+    #[allow(dead_code)]
+    pub fn get_mut(&mut self, pos: Vector2D) -> Option<&mut T> {
+        self.cells.get_mut(&pos)
+    }
+
+    #[allow(dead_code)]
+    pub fn insert(&mut self, pos: Vector2D, value: T) -> Option<T> {
+        if self.cells.is_empty() {
+            self.min = pos;
+            self.max = pos;
+        } else {
+            self.min = self.min.zip_with(&pos, |&a, &b| a.min(b));
+            self.max = self.max.zip_with(&pos, |&a, &b| a.max(b));
+        }
+        self.cells.insert(pos, value)
+    }
+
+    /// Removes a cell. The bounds of the grid never shrink.
+    #[allow(dead_code)]
+    pub fn remove(&mut self, pos: Vector2D) -> Option<T> {
+        self.cells.remove(&pos)
+    }
+
+    #[allow(dead_code)]
+    pub fn iter(&self) -> impl Iterator<Item = (Vector2D, &T)> + '_ {
+        self.cells.iter().map(|(&pos, cell)| (pos, cell))
+    }
+
+    #[allow(dead_code)]
+    pub fn neighbours(&self, pos: Vector2D) -> impl Iterator<Item = (Vector2D, &T)> + '_ {
+        pos.neighbours()
+            .filter_map(|neighbour| Some((neighbour, self.get(neighbour)?)))
+    }
+
+    #[allow(dead_code)]
+    pub fn neighbours_diagonal(&self, pos: Vector2D) -> impl Iterator<Item = (Vector2D, &T)> + '_ {
+        pos.neighbours_diagonal()
+            .filter_map(|neighbour| Some((neighbour, self.get(neighbour)?)))
+    }
+
+    /// Converts to a dense grid covering the same bounds, filling empty cells with `empty`.
+    #[allow(dead_code)]
+    pub fn to_dense(&self, empty: T) -> Grid<T>
+    where
+        T: Clone,
+    {
+        let mut grid = Grid::with_bounds(self.min, self.max, empty);
+        for (&pos, cell) in &self.cells {
+            grid[pos] = cell.clone();
+        }
+        grid
+    }
+}
+
+impl<T> Default for SparseGrid<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> FromIterator<(Vector2D, T)> for SparseGrid<T> {
+    fn from_iter<I: IntoIterator<Item = (Vector2D, T)>>(iter: I) -> Self {
+        let mut grid = Self::new();
+        for (pos, cell) in iter {
+            grid.insert(pos, cell);
+        }
+        grid
+    }
+}
+
+impl<T: Display> Display for SparseGrid<T> {
+    /// Renders the grid, drawing empty cells as spaces.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for y in self.min.y()..=self.max.y() {
+            if y > self.min.y() {
+                writeln!(f)?;
+            }
+            for x in self.min.x()..=self.max.x() {
+                match self.get(Vector2D::new(x, y)) {
+                    Some(cell) => write!(f, "{}", cell)?,
+                    None => write!(f, " ")?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Grid<char> {
+        Grid::parse(0, "ab\ncd\nef", Some).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let grid = example();
+        assert_eq!((grid.width(), grid.height()), (2, 3));
+        assert_eq!(grid[Vector2D::new(1, 0)], 'b');
+        assert_eq!(grid[Vector2D::new(0, 2)], 'e');
+        assert_eq!(grid.get(Vector2D::new(2, 0)), None);
+        assert_eq!(grid.get(Vector2D::new(0, -1)), None);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let err = Grid::parse(0, "..\n.x", |c| (c == '.').then_some(c)).unwrap_err();
+        assert_eq!((err.line, err.column), (2, 2));
+        let err = Grid::parse(0, "..\n.", |c| (c == '.').then_some(c)).unwrap_err();
+        assert_eq!((err.line, err.column), (2, 2));
+    }
+
+    #[test]
+    fn test_rows_and_columns() {
+        let grid = example();
+        let rows = grid
+            .rows()
+            .map(|row| row.iter().collect())
+            .collect::<Vec<String>>();
+        assert_eq!(rows, vec!["ab", "cd", "ef"]);
+        let columns = grid
+            .columns()
+            .map(|column| column.collect())
+            .collect::<Vec<String>>();
+        assert_eq!(columns, vec!["ace", "bdf"]);
+    }
+
+    #[test]
+    fn test_neighbours() {
+        let grid = example();
+        let neighbours = grid
+            .neighbours(Vector2D::new(0, 0))
+            .map(|(_, &c)| c)
+            .collect::<String>();
+        assert_eq!(neighbours, "bc");
+        let neighbours = grid
+            .neighbours_diagonal(Vector2D::new(0, 1))
+            .map(|(_, &c)| c)
+            .collect::<String>();
+        assert_eq!(neighbours, "abdef");
+    }
+
+    #[test]
+    fn test_grow_to_include() {
+        let mut grid = example();
+        grid.grow_to_include(Vector2D::new(-2, 4), '.');
+        assert!(grid.contains(Vector2D::new(-2, 4)));
+        assert_eq!(grid[Vector2D::new(0, 0)], 'a');
+        assert_eq!(grid[Vector2D::new(1, 2)], 'f');
+        assert_eq!(grid[Vector2D::new(-2, 4)], '.');
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(example().to_string(), "ab\ncd\nef");
+    }
+
+    #[test]
+    fn test_sparse() {
+        let mut grid = SparseGrid::new();
+        grid.insert(Vector2D::new(2, -1), '#');
+        grid.insert(Vector2D::new(-1, 1), '#');
+        assert_eq!(grid.min(), Vector2D::new(-1, -1));
+        assert_eq!(grid.max(), Vector2D::new(2, 1));
+        assert_eq!(grid.to_string(), "   #\n    \n#   ");
+        assert_eq!(grid.to_dense('.').to_string(), "...#\n....\n#...");
+    }
+}
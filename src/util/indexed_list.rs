@@ -0,0 +1,295 @@
+/// A sequence that supports finding and moving elements in O(log n).
+///
+/// Every element gets a stable handle when it is pushed: the n-th pushed element has handle `n`.
+/// Handles keep referring to the same element no matter where it gets moved.
+///
+/// Implemented as an implicit treap: a randomized binary tree ordered by position,
+/// where each node knows the size of its subtree and its parent.
+#[derive(Debug, Clone)]
+pub struct IndexedList<T> {
+    nodes: Vec<Node<T>>,
+    root: usize,
+    seed: u32,
+}
+
+#[derive(Debug, Clone)]
+struct Node<T> {
+    value: T,
+    priority: u32,
+    size: usize,
+    left: usize,
+    right: usize,
+    parent: usize,
+}
+
+const NIL: usize = usize::MAX;
+
+impl<T> IndexedList<T> {
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            root: NIL,
+            seed: 0x2545_f491,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Appends a value to the end of the list, and returns its handle.
+    pub fn push(&mut self, value: T) -> usize {
+        let handle = self.nodes.len();
+        let priority = self.next_priority();
+        self.nodes.push(Node {
+            value,
+            priority,
+            size: 1,
+            left: NIL,
+            right: NIL,
+            parent: NIL,
+        });
+        self.root = self.merge(self.root, handle);
+        handle
+    }
+
+    /// The value with the given handle.
+    #[allow(dead_code)]
+    pub fn value(&self, handle: usize) -> &T {
+        &self.nodes[handle].value
+    }
+
+    /// The value at the given position.
+    #[allow(dead_code)]
+    pub fn get(&self, mut index: usize) -> Option<&T> {
+        let mut node = self.root;
+        while node != NIL {
+            let left_size = self.size(self.nodes[node].left);
+            if index < left_size {
+                node = self.nodes[node].left;
+            } else if index == left_size {
+                return Some(&self.nodes[node].value);
+            } else {
+                index -= left_size + 1;
+                node = self.nodes[node].right;
+            }
+        }
+        None
+    }
+
+    /// The current position of the value with the given handle.
+    pub fn index_of(&self, handle: usize) -> usize {
+        let mut index = self.size(self.nodes[handle].left);
+        let mut node = handle;
+        while self.nodes[node].parent != NIL {
+            let parent = self.nodes[node].parent;
+            if self.nodes[parent].right == node {
+                index += self.size(self.nodes[parent].left) + 1;
+            }
+            node = parent;
+        }
+        index
+    }
+
+    /// Moves the value with the given handle, such that it ends up at position `index`.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn move_to(&mut self, handle: usize, index: usize) {
+        assert!(index < self.len(), "index out of bounds");
+        self.unlink(handle);
+        let (before, after) = self.split(self.root, index);
+        let before = self.merge(before, handle);
+        self.root = self.merge(before, after);
+    }
+
+    /// Takes a node out of the tree, leaving it as a single detached node.
+    fn unlink(&mut self, node: usize) {
+        let Node {
+            left,
+            right,
+            parent,
+            ..
+        } = self.nodes[node];
+        let children = self.merge(left, right);
+        if parent == NIL {
+            self.root = children;
+        } else if self.nodes[parent].left == node {
+            self.set_left(parent, children);
+        } else {
+            self.set_right(parent, children);
+        }
+        let mut ancestor = parent;
+        while ancestor != NIL {
+            self.nodes[ancestor].size -= 1;
+            ancestor = self.nodes[ancestor].parent;
+        }
+        let node = &mut self.nodes[node];
+        node.left = NIL;
+        node.right = NIL;
+        node.parent = NIL;
+        node.size = 1;
+    }
+
+    /// Iterates over the values in order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        let mut stack = Vec::new();
+        let mut node = self.root;
+        std::iter::from_fn(move || {
+            while node != NIL {
+                stack.push(node);
+                node = self.nodes[node].left;
+            }
+            let current = stack.pop()?;
+            node = self.nodes[current].right;
+            Some(&self.nodes[current].value)
+        })
+    }
+
+    fn next_priority(&mut self) -> u32 {
+        // xorshift32
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.seed
+    }
+
+    fn size(&self, node: usize) -> usize {
+        if node == NIL {
+            0
+        } else {
+            self.nodes[node].size
+        }
+    }
+
+    fn set_left(&mut self, node: usize, child: usize) {
+        self.nodes[node].left = child;
+        if child != NIL {
+            self.nodes[child].parent = node;
+        }
+    }
+
+    fn set_right(&mut self, node: usize, child: usize) {
+        self.nodes[node].right = child;
+        if child != NIL {
+            self.nodes[child].parent = node;
+        }
+    }
+
+    fn make_root(&mut self, node: usize) -> usize {
+        if node != NIL {
+            self.nodes[node].parent = NIL;
+        }
+        node
+    }
+
+    fn update_size(&mut self, node: usize) {
+        self.nodes[node].size =
+            self.size(self.nodes[node].left) + self.size(self.nodes[node].right) + 1;
+    }
+
+    /// Splits a tree into its first `index` values and the rest.
+    fn split(&mut self, node: usize, index: usize) -> (usize, usize) {
+        if node == NIL {
+            return (NIL, NIL);
+        }
+        let left_size = self.size(self.nodes[node].left);
+        if index <= left_size {
+            let (before, after) = self.split(self.nodes[node].left, index);
+            self.set_left(node, after);
+            self.update_size(node);
+            (self.make_root(before), self.make_root(node))
+        } else {
+            let (before, after) = self.split(self.nodes[node].right, index - left_size - 1);
+            self.set_right(node, before);
+            self.update_size(node);
+            (self.make_root(node), self.make_root(after))
+        }
+    }
+
+    /// Joins two trees, with all values of `left` coming before those of `right`.
+    fn merge(&mut self, left: usize, right: usize) -> usize {
+        if left == NIL {
+            return self.make_root(right);
+        }
+        if right == NIL {
+            return self.make_root(left);
+        }
+        if self.nodes[left].priority > self.nodes[right].priority {
+            let merged = self.merge(self.nodes[left].right, right);
+            self.set_right(left, merged);
+            self.update_size(left);
+            self.make_root(left)
+        } else {
+            let merged = self.merge(left, self.nodes[right].left);
+            self.set_left(right, merged);
+            self.update_size(right);
+            self.make_root(right)
+        }
+    }
+}
+
+impl<T> Default for IndexedList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> FromIterator<T> for IndexedList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Self::new();
+        for value in iter {
+            list.push(value);
+        }
+        list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push() {
+        let list = "abcde".chars().collect::<IndexedList<_>>();
+        assert_eq!(list.len(), 5);
+        assert_eq!(list.iter().collect::<String>(), "abcde");
+        assert_eq!(list.get(3), Some(&'d'));
+        assert_eq!(list.get(5), None);
+        assert!((0..5).all(|handle| list.index_of(handle) == handle));
+    }
+
+    #[test]
+    fn test_move_to() {
+        let mut list = "abcde".chars().collect::<IndexedList<_>>();
+        list.move_to(0, 3);
+        assert_eq!(list.iter().collect::<String>(), "bcdae");
+        list.move_to(4, 0);
+        assert_eq!(list.iter().collect::<String>(), "ebcda");
+        list.move_to(2, 2);
+        assert_eq!(list.iter().collect::<String>(), "ebcda");
+        assert_eq!(list.index_of(0), 4);
+        assert_eq!(list.index_of(4), 0);
+        assert_eq!(list.value(3), &'d');
+    }
+
+    #[test]
+    fn test_matches_vec() {
+        let mut list = (0..100).collect::<IndexedList<_>>();
+        let mut vec = (0..100).collect::<Vec<_>>();
+        for i in 0..1000 {
+            let handle = (i * 37) % 100;
+            let index = (i * 53 + 11) % 100;
+            let old_index = vec.iter().position(|&x| x == handle).unwrap();
+            assert_eq!(list.index_of(handle), old_index);
+            let value = vec.remove(old_index);
+            vec.insert(index, value);
+            list.move_to(handle, index);
+        }
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), vec);
+    }
+}
@@ -18,6 +18,15 @@ pub fn gcd_64(mut a: i64, mut b: i64) -> i64 {
     b.abs()
 }
 
+pub fn gcd_128(mut a: i128, mut b: i128) -> i128 {
+    while a != 0 {
+        let old_a = a;
+        a = b % a;
+        b = old_a;
+    }
+    b.abs()
+}
+
 #[allow(dead_code)]
 pub fn lcm(a: i32, b: i32) -> i32 {
     (a * b).abs() / gcd(a, b)
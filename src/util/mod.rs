@@ -1,7 +1,23 @@
+pub use bounding_box::*;
+pub use cycle::*;
+pub use grid::*;
+pub use indexed_list::*;
 pub use math::*;
+pub use ocr::*;
+pub use parse::*;
+pub use rational::*;
 pub use slice::*;
 pub use vector::*;
+pub use vm::*;
 
+mod bounding_box;
+mod cycle;
+mod grid;
+mod indexed_list;
 mod math;
+mod ocr;
+mod parse;
+mod rational;
 mod slice;
 mod vector;
+mod vm;
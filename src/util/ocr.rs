@@ -0,0 +1,252 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// The fonts used by Advent of Code puzzles that draw letters on a screen.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Font {
+    /// Letters of 4×6 pixels, e.g. 2022 day 10.
+    Small,
+    /// Letters of 6×10 pixels, e.g. 2018 day 10.
+    Large,
+}
+
+impl Font {
+    fn from_height(height: usize) -> Option<Self> {
+        match height {
+            6 => Some(Font::Small),
+            10 => Some(Font::Large),
+            _ => None,
+        }
+    }
+
+    /// The width of a single letter, including the spacing after it.
+    fn cell_width(self) -> usize {
+        match self {
+            Font::Small => 5,
+            Font::Large => 8,
+        }
+    }
+
+    fn glyphs(self) -> &'static [(char, &'static str)] {
+        match self {
+            Font::Small => SMALL_GLYPHS,
+            Font::Large => LARGE_GLYPHS,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum OcrMode {
+    /// Fail on any letter that isn't recognized.
+    Strict,
+    /// Replace unrecognized letters with `?`.
+    #[allow(dead_code)]
+    Lenient,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum OcrError {
+    /// The screen doesn't have the height of any known font.
+    UnknownHeight(usize),
+    /// The letter at the given (0-based) position isn't recognized.
+    UnknownLetter { position: usize, glyph: String },
+}
+
+impl Display for OcrError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OcrError::UnknownHeight(height) => {
+                write!(f, "no font with a height of {} pixels", height)
+            }
+            OcrError::UnknownLetter { position, glyph } => {
+                write!(f, "unknown letter at position {}:\n{}", position, glyph)
+            }
+        }
+    }
+}
+
+impl Error for OcrError {}
+
+fn is_lit(c: char) -> bool {
+    c == '#' || c == '█'
+}
+
+/// Reads the letters drawn on a screen, where lit pixels are `#` (or `█`).
+/// The font is chosen based on the height of the screen.
+pub fn read_letters(screen: &str, mode: OcrMode) -> Result<String, OcrError> {
+    let rows = screen
+        .lines()
+        .filter(|row| !row.trim().is_empty())
+        .map(|row| row.chars().map(is_lit).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let font = Font::from_height(rows.len()).ok_or(OcrError::UnknownHeight(rows.len()))?;
+    let cell_width = font.cell_width();
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let cell_count = (width + cell_width - 1) / cell_width;
+
+    let mut letters = String::new();
+    for position in 0..cell_count {
+        let glyph = rows
+            .iter()
+            .map(|row| {
+                (position * cell_width..(position + 1) * cell_width)
+                    .map(|x| if row.get(x) == Some(&true) { '#' } else { '.' })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        if glyph.iter().all(|row| !row.contains('#')) {
+            // Skip trailing blank space.
+            continue;
+        }
+        let letter = font.glyphs().iter().find(|(_, pattern)| {
+            pattern.lines().zip(&glyph).all(|(pattern, row)| {
+                row.starts_with(pattern) && !row[pattern.len()..].contains('#')
+            })
+        });
+        match (letter, mode) {
+            (Some(&(letter, _)), _) => letters.push(letter),
+            (None, OcrMode::Lenient) => letters.push('?'),
+            (None, OcrMode::Strict) => {
+                return Err(OcrError::UnknownLetter {
+                    position,
+                    glyph: glyph.join("\n"),
+                })
+            }
+        }
+    }
+    Ok(letters)
+}
+
+const SMALL_GLYPHS: &[(char, &str)] = &[
+    ('A', ".##.\n#..#\n#..#\n####\n#..#\n#..#"),
+    ('B', "###.\n#..#\n###.\n#..#\n#..#\n###."),
+    ('C', ".##.\n#..#\n#...\n#...\n#..#\n.##."),
+    ('E', "####\n#...\n###.\n#...\n#...\n####"),
+    ('F', "####\n#...\n###.\n#...\n#...\n#..."),
+    ('G', ".##.\n#..#\n#...\n#.##\n#..#\n.###"),
+    ('H', "#..#\n#..#\n####\n#..#\n#..#\n#..#"),
+    ('I', ".###\n..#.\n..#.\n..#.\n..#.\n.###"),
+    ('J', "..##\n...#\n...#\n...#\n#..#\n.##."),
+    ('K', "#..#\n#.#.\n##..\n#.#.\n#.#.\n#..#"),
+    ('L', "#...\n#...\n#...\n#...\n#...\n####"),
+    ('O', ".##.\n#..#\n#..#\n#..#\n#..#\n.##."),
+    ('P', "###.\n#..#\n#..#\n###.\n#...\n#..."),
+    ('R', "###.\n#..#\n#..#\n###.\n#.#.\n#..#"),
+    ('S', ".###\n#...\n#...\n.##.\n...#\n###."),
+    ('U', "#..#\n#..#\n#..#\n#..#\n#..#\n.##."),
+    ('Y', "#...#\n#...#\n.#.#.\n..#..\n..#..\n..#.."),
+    ('Z', "####\n...#\n..#.\n.#..\n#...\n####"),
+];
+
+const LARGE_GLYPHS: &[(char, &str)] = &[
+    (
+        'A',
+        "..##..\n.#..#.\n#....#\n#....#\n#....#\n######\n#....#\n#....#\n#....#\n#....#",
+    ),
+    (
+        'B',
+        "#####.\n#....#\n#....#\n#....#\n#####.\n#....#\n#....#\n#....#\n#....#\n#####.",
+    ),
+    (
+        'C',
+        ".####.\n#....#\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#....#\n.####.",
+    ),
+    (
+        'E',
+        "######\n#.....\n#.....\n#.....\n#####.\n#.....\n#.....\n#.....\n#.....\n######",
+    ),
+    (
+        'F',
+        "######\n#.....\n#.....\n#.....\n#####.\n#.....\n#.....\n#.....\n#.....\n#.....",
+    ),
+    (
+        'G',
+        ".####.\n#....#\n#.....\n#.....\n#.....\n#..###\n#....#\n#....#\n#...##\n.###.#",
+    ),
+    (
+        'H',
+        "#....#\n#....#\n#....#\n#....#\n######\n#....#\n#....#\n#....#\n#....#\n#....#",
+    ),
+    (
+        'J',
+        "...###\n....#.\n....#.\n....#.\n....#.\n....#.\n....#.\n#...#.\n#...#.\n.###..",
+    ),
+    (
+        'K',
+        "#....#\n#...#.\n#..#..\n#.#...\n##....\n##....\n#.#...\n#..#..\n#...#.\n#....#",
+    ),
+    (
+        'L',
+        "#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n######",
+    ),
+    (
+        'N',
+        "#....#\n##...#\n##...#\n#.#..#\n#.#..#\n#..#.#\n#..#.#\n#...##\n#...##\n#....#",
+    ),
+    (
+        'P',
+        "#####.\n#....#\n#....#\n#....#\n#####.\n#.....\n#.....\n#.....\n#.....\n#.....",
+    ),
+    (
+        'R',
+        "#####.\n#....#\n#....#\n#....#\n#####.\n#..#..\n#...#.\n#...#.\n#....#\n#....#",
+    ),
+    (
+        'X',
+        "#....#\n#....#\n.#..#.\n.#..#.\n..##..\n..##..\n.#..#.\n.#..#.\n#....#\n#....#",
+    ),
+    (
+        'Z',
+        "######\n.....#\n.....#\n....#.\n...#..\n..#...\n.#....\n#.....\n#.....\n######",
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small() {
+        let screen = r"
+###..#..#..##..#..#.#..#.###..####.#..#.
+#..#.#..#.#..#.#.#..#..#.#..#.#....#.#..
+#..#.#..#.#..#.##...####.###..###..##...
+###..#..#.####.#.#..#..#.#..#.#....#.#..
+#.#..#..#.#..#.#.#..#..#.#..#.#....#.#..
+#..#..##..#..#.#..#.#..#.###..####.#..#.
+";
+        assert_eq!(
+            read_letters(screen, OcrMode::Strict),
+            Ok("RUAKHBEK".to_string())
+        );
+    }
+
+    #[test]
+    fn test_large() {
+        let screen = r"
+#....#..######
+#....#....#...
+#....#....#...
+#....#....#...
+######....#...
+#....#....#...
+#....#....#...
+#....#....#...
+#....#....#...
+#....#..######
+";
+        assert_eq!(read_letters(screen, OcrMode::Lenient), Ok("H?".to_string()));
+        assert!(matches!(
+            read_letters(screen, OcrMode::Strict),
+            Err(OcrError::UnknownLetter { position: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_unknown_height() {
+        assert_eq!(
+            read_letters("#\n#\n#", OcrMode::Lenient),
+            Err(OcrError::UnknownHeight(3))
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// An error encountered while parsing puzzle input.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseError {
+    /// The puzzle day whose input was being parsed.
+    pub day: u32,
+    /// The 1-based number of the offending line.
+    pub line: usize,
+    /// The 1-based column (in characters) where the problem starts.
+    pub column: usize,
+    /// The full text of the offending line.
+    pub text: String,
+    /// What went wrong.
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(
+        day: u32,
+        line: usize,
+        column: usize,
+        text: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            day,
+            line,
+            column,
+            text: text.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Moves this error to a different line number.
+    ///
+    /// Useful for `FromStr` implementations, which only ever see a single line.
+    pub fn at_line(self, line: usize) -> Self {
+        Self { line, ..self }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "day {}, line {}, column {}: {}",
+            self.day, self.line, self.column, self.message
+        )?;
+        writeln!(f, "    {}", self.text)?;
+        write!(f, "    {}^", " ".repeat(self.column.saturating_sub(1)))
+    }
+}
+
+impl Error for ParseError {}
+
+/// A single line of puzzle input, which remembers where it came from
+/// so parse errors can point at the offending text.
+#[derive(Debug, Copy, Clone)]
+pub struct Line<'a> {
+    pub day: u32,
+    pub number: usize,
+    pub text: &'a str,
+}
+
+impl<'a> Line<'a> {
+    pub fn new(day: u32, number: usize, text: &'a str) -> Self {
+        Self { day, number, text }
+    }
+
+    /// Creates an error pointing at `at`, which should be a slice of this line.
+    /// If it isn't, the error points at the start of the line.
+    pub fn error(&self, at: &str, message: impl Into<String>) -> ParseError {
+        let start = self.text.as_ptr() as usize;
+        let offset = (at.as_ptr() as usize)
+            .checked_sub(start)
+            .filter(|&offset| offset <= self.text.len() && self.text.is_char_boundary(offset))
+            .unwrap_or(0);
+        let column = self.text[..offset].chars().count() + 1;
+        ParseError::new(self.day, self.number, column, self.text, message)
+    }
+
+    pub fn split_once(
+        &self,
+        s: &'a str,
+        delimiter: &str,
+    ) -> Result<(&'a str, &'a str), ParseError> {
+        s.split_once(delimiter)
+            .ok_or_else(|| self.error(s, format!("expected `{}`", delimiter)))
+    }
+
+    pub fn strip_prefix(&self, s: &'a str, prefix: &str) -> Result<&'a str, ParseError> {
+        s.strip_prefix(prefix)
+            .ok_or_else(|| self.error(s, format!("expected `{}`", prefix)))
+    }
+
+    pub fn strip_suffix(&self, s: &'a str, suffix: &str) -> Result<&'a str, ParseError> {
+        s.strip_suffix(suffix)
+            .ok_or_else(|| self.error(&s[s.len()..], format!("expected `{}`", suffix)))
+    }
+
+    /// Parses a part of this line, e.g. a number.
+    pub fn parse<T>(&self, s: &str) -> Result<T, ParseError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        s.parse()
+            .map_err(|err| self.error(s, format!("invalid value `{}`: {}", s, err)))
+    }
+
+    /// Parses the whole line using a `FromStr` implementation that reports its own errors.
+    pub fn parse_line<T>(&self) -> Result<T, ParseError>
+    where
+        T: FromStr<Err = ParseError>,
+    {
+        self.text
+            .parse()
+            .map_err(|err: ParseError| err.at_line(self.number))
+    }
+}
+
+/// Splits the puzzle input into lines.
+pub fn lines(day: u32, input: &str) -> impl Iterator<Item = Line<'_>> {
+    input
+        .lines()
+        .enumerate()
+        .map(move |(i, text)| Line::new(day, i + 1, text))
+}
+
+/// Splits the puzzle input into blocks of lines, separated by blank lines.
+pub fn blocks(day: u32, input: &str) -> Vec<Vec<Line<'_>>> {
+    let mut blocks = vec![Vec::new()];
+    for line in lines(day, input) {
+        if line.text.trim().is_empty() {
+            blocks.push(Vec::new());
+        } else {
+            blocks.last_mut().unwrap().push(line);
+        }
+    }
+    blocks.retain(|block| !block.is_empty());
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_column() {
+        let line = Line::new(4, 3, "2-4,6-x");
+        let (_, right) = line.split_once(line.text, ",").unwrap();
+        let (_, end) = line.split_once(right, "-").unwrap();
+        let err = line.parse::<u32>(end).unwrap_err();
+        assert_eq!(err.day, 4);
+        assert_eq!(err.line, 3);
+        assert_eq!(err.column, 7);
+        assert_eq!(err.text, "2-4,6-x");
+    }
+
+    #[test]
+    fn test_display() {
+        let line = Line::new(9, 2, "X 4");
+        let err = line.error(line.text, "invalid direction");
+        assert_eq!(
+            err.to_string(),
+            "day 9, line 2, column 1: invalid direction\n    X 4\n    ^"
+        );
+    }
+
+    #[test]
+    fn test_blocks() {
+        let blocks = blocks(1, "1\n2\n\n3\n\n\n4\n");
+        let numbers = blocks
+            .iter()
+            .map(|block| block.iter().map(|line| line.number).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(numbers, vec![vec![1, 2], vec![4], vec![7]]);
+    }
+}
@@ -0,0 +1,210 @@
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use crate::util::gcd_128;
+
+/// An exact fraction, always stored in lowest terms with a positive denominator.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Rational {
+    numerator: i128,
+    denominator: i128,
+}
+
+impl Rational {
+    pub const ZERO: Rational = Rational {
+        numerator: 0,
+        denominator: 1,
+    };
+    pub const ONE: Rational = Rational {
+        numerator: 1,
+        denominator: 1,
+    };
+
+    /// Creates the fraction `numerator / denominator`.
+    ///
+    /// Panics if the denominator is zero.
+    pub fn new(numerator: i128, denominator: i128) -> Self {
+        assert_ne!(denominator, 0, "denominator must not be zero");
+        let divisor = gcd_128(numerator, denominator) * denominator.signum();
+        Self {
+            numerator: numerator / divisor,
+            denominator: denominator / divisor,
+        }
+    }
+
+    pub fn from_integer(value: i128) -> Self {
+        Self {
+            numerator: value,
+            denominator: 1,
+        }
+    }
+
+    pub fn numerator(self) -> i128 {
+        self.numerator
+    }
+
+    pub fn denominator(self) -> i128 {
+        self.denominator
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.numerator == 0
+    }
+
+    pub fn is_integer(self) -> bool {
+        self.denominator == 1
+    }
+
+    /// The value as an integer, if it is one.
+    pub fn to_integer(self) -> Option<i128> {
+        self.is_integer().then_some(self.numerator)
+    }
+
+    /// Returns `1 / self`, or `None` if `self` is zero.
+    pub fn recip(self) -> Option<Self> {
+        (!self.is_zero()).then(|| Self::new(self.denominator, self.numerator))
+    }
+
+    /// Returns `self + other`, or `None` if that overflows.
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        let numerator = (self.numerator.checked_mul(other.denominator)?)
+            .checked_add(other.numerator.checked_mul(self.denominator)?)?;
+        let denominator = self.denominator.checked_mul(other.denominator)?;
+        Some(Self::new(numerator, denominator))
+    }
+
+    /// Returns `self - other`, or `None` if that overflows.
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.checked_add(other.checked_neg()?)
+    }
+
+    /// Returns `self * other`, or `None` if that overflows.
+    pub fn checked_mul(self, other: Self) -> Option<Self> {
+        // Cancel common factors first, so the products stay as small as possible.
+        let left = gcd_128(self.numerator, other.denominator);
+        let right = gcd_128(other.numerator, self.denominator);
+        Some(Self::new(
+            (self.numerator / left).checked_mul(other.numerator / right)?,
+            (self.denominator / right).checked_mul(other.denominator / left)?,
+        ))
+    }
+
+    /// Returns `-self`, or `None` if that overflows.
+    pub fn checked_neg(self) -> Option<Self> {
+        Some(Self {
+            numerator: self.numerator.checked_neg()?,
+            denominator: self.denominator,
+        })
+    }
+
+    /// Returns `self / other`, or `None` if `other` is zero or the result overflows.
+    pub fn checked_div(self, other: Self) -> Option<Self> {
+        self.checked_mul(other.recip()?)
+    }
+}
+
+impl From<i64> for Rational {
+    fn from(value: i64) -> Self {
+        Self::from_integer(value.into())
+    }
+}
+
+impl Add for Rational {
+    type Output = Self;
+
+    /// Panics on overflow, see `checked_add()` for a non-panicking version.
+    fn add(self, other: Self) -> Self {
+        self.checked_add(other).expect("overflow")
+    }
+}
+
+impl Sub for Rational {
+    type Output = Self;
+
+    /// Panics on overflow, see `checked_sub()` for a non-panicking version.
+    fn sub(self, other: Self) -> Self {
+        self.checked_sub(other).expect("overflow")
+    }
+}
+
+impl Mul for Rational {
+    type Output = Self;
+
+    /// Panics on overflow, see `checked_mul()` for a non-panicking version.
+    fn mul(self, other: Self) -> Self {
+        self.checked_mul(other).expect("overflow")
+    }
+}
+
+impl Div for Rational {
+    type Output = Self;
+
+    /// Panics if `other` is zero, see `checked_div()` for a non-panicking version.
+    fn div(self, other: Self) -> Self {
+        self.checked_div(other).expect("division by zero")
+    }
+}
+
+impl Neg for Rational {
+    type Output = Self;
+
+    /// Panics on overflow, see `checked_neg()` for a non-panicking version.
+    fn neg(self) -> Self {
+        self.checked_neg().expect("overflow")
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let r = Rational::new(6, -4);
+        assert_eq!((r.numerator(), r.denominator()), (-3, 2));
+        assert_eq!(Rational::new(0, -5), Rational::ZERO);
+        assert_eq!(r.to_string(), "-3/2");
+        assert_eq!(Rational::new(8, 4).to_integer(), Some(2));
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = Rational::new(1, 2);
+        let b = Rational::new(1, 3);
+        assert_eq!(a + b, Rational::new(5, 6));
+        assert_eq!(a - b, Rational::new(1, 6));
+        assert_eq!(a * b, Rational::new(1, 6));
+        assert_eq!(a / b, Rational::new(3, 2));
+        assert_eq!(a.checked_div(Rational::ZERO), None);
+        assert_eq!(-a + a, Rational::ZERO);
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let big = Rational::from_integer(i128::MAX / 2 + 1);
+        assert_eq!(big.checked_add(big), None);
+        assert_eq!(big.checked_mul(Rational::from(2)), None);
+        assert_eq!(Rational::from_integer(i128::MIN).checked_neg(), None);
+        assert_eq!(
+            (-big).checked_sub(big),
+            Some(Rational::from_integer(i128::MIN))
+        );
+        // Common factors cancel before multiplying
+        let a = Rational::new(i128::MAX, 3);
+        assert_eq!(
+            a.checked_mul(Rational::new(3, i128::MAX)),
+            Some(Rational::ONE)
+        );
+        assert_eq!(a.checked_div(a), Some(Rational::ONE));
+    }
+}
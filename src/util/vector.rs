@@ -1,16 +1,86 @@
 use std::fmt::{Debug, Display, Formatter};
-use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use std::hash::Hash;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, Sub, SubAssign};
+
+/// A signed integer type that can be used as the coordinates of a `Vector`.
+pub trait Scalar:
+    Copy
+    + Eq
+    + Ord
+    + Hash
+    + Default
+    + Debug
+    + Display
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Rem<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + DivAssign
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn abs(self) -> Self;
+    fn signum(self) -> Self;
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_sub(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+}
+
+macro_rules! impl_scalar {
+    ($($t:ty),*) => {
+        $(
+            impl Scalar for $t {
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+
+                #[inline]
+                fn abs(self) -> Self {
+                    <$t>::abs(self)
+                }
+
+                #[inline]
+                fn signum(self) -> Self {
+                    <$t>::signum(self)
+                }
+
+                #[inline]
+                fn checked_add(self, other: Self) -> Option<Self> {
+                    <$t>::checked_add(self, other)
+                }
+
+                #[inline]
+                fn checked_sub(self, other: Self) -> Option<Self> {
+                    <$t>::checked_sub(self, other)
+                }
+
+                #[inline]
+                fn checked_mul(self, other: Self) -> Option<Self> {
+                    <$t>::checked_mul(self, other)
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar!(i8, i16, i32, i64, i128, isize);
 
 #[derive(Eq, PartialEq, Copy, Clone, Hash)]
-pub struct Vector<const N: usize> {
-    pub coords: [i32; N],
+pub struct Vector<T, const N: usize> {
+    pub coords: [T; N],
 }
 
-#[allow(dead_code)]
-impl<const N: usize> Vector<N> {
+impl<T: Scalar, const N: usize> Vector<T, N> {
     #[inline]
     pub const fn zero() -> Self {
-        Self { coords: [0i32; N] }
+        Self {
+            coords: [T::ZERO; N],
+        }
     }
 
     #[inline]
@@ -20,27 +90,59 @@ impl<const N: usize> Vector<N> {
     }
 
     #[inline]
-    pub fn manhattan_distance(&self) -> i32 {
-        self.coords.iter().map(|x| x.abs()).sum()
+    pub fn manhattan_distance(&self) -> T {
+        self.coords.iter().fold(T::ZERO, |sum, &x| sum + x.abs())
+    }
+
+    /// The largest absolute value of any coordinate, i.e. the number of king moves
+    /// needed to get from the origin to this vector.
+    #[inline]
+    pub fn chebyshev_distance(&self) -> T {
+        self.coords.iter().map(|x| x.abs()).max().unwrap_or(T::ZERO)
+    }
+
+    #[inline]
+    pub fn dot(&self, other: &Vector<T, N>) -> T {
+        self.coords
+            .iter()
+            .zip(other.coords.iter())
+            .fold(T::ZERO, |sum, (&x, &y)| sum + x * y)
+    }
+
+    #[inline]
+    pub fn signum(self) -> Self {
+        self.map(|x| x.signum())
+    }
+
+    /// The component-wise minimum of two vectors.
+    #[inline]
+    pub fn min(&self, other: &Vector<T, N>) -> Self {
+        self.zip_with(other, |&x, &y| x.min(y))
+    }
+
+    /// The component-wise maximum of two vectors.
+    #[inline]
+    pub fn max(&self, other: &Vector<T, N>) -> Self {
+        self.zip_with(other, |&x, &y| x.max(y))
     }
 
     #[inline]
-    pub fn for_each(&mut self, f: impl FnMut(&mut i32)) {
+    pub fn for_each(&mut self, f: impl FnMut(&mut T)) {
         self.coords.iter_mut().for_each(f);
     }
 
     #[inline]
-    pub fn map_in_place(&mut self, mut f: impl FnMut(&i32) -> i32) {
+    pub fn map_in_place(&mut self, mut f: impl FnMut(&T) -> T) {
         self.for_each(|x| *x = f(x))
     }
 
     #[inline]
-    pub fn map(&self, f: impl FnMut(&i32) -> i32) -> Self {
+    pub fn map(&self, f: impl FnMut(&T) -> T) -> Self {
         Self::from_iter(self.coords.iter().map(f))
     }
 
     #[inline]
-    pub fn zip_in_place(&mut self, other: &Vector<N>, mut f: impl FnMut(&mut i32, &i32)) {
+    pub fn zip_in_place(&mut self, other: &Vector<T, N>, mut f: impl FnMut(&mut T, &T)) {
         self.coords
             .iter_mut()
             .zip(other.coords.iter())
@@ -48,7 +150,7 @@ impl<const N: usize> Vector<N> {
     }
 
     #[inline]
-    pub fn zip_with(&self, other: &Vector<N>, mut f: impl FnMut(&i32, &i32) -> i32) -> Self {
+    pub fn zip_with(&self, other: &Vector<T, N>, mut f: impl FnMut(&T, &T) -> T) -> Self {
         Self::from_iter(
             self.coords
                 .iter()
@@ -57,8 +159,60 @@ impl<const N: usize> Vector<N> {
         )
     }
 
-    fn from_iter(iter: impl Iterator<Item = i32>) -> Self {
-        let mut coords = [0i32; N];
+    /// Like `zip_with()`, but gives up as soon as `f` returns `None`.
+    #[inline]
+    pub fn try_zip_with(
+        &self,
+        other: &Vector<T, N>,
+        mut f: impl FnMut(&T, &T) -> Option<T>,
+    ) -> Option<Self> {
+        let mut coords = self.coords;
+        for (x, y) in coords.iter_mut().zip(other.coords.iter()) {
+            *x = f(x, y)?;
+        }
+        Some(coords.into())
+    }
+
+    #[inline]
+    pub fn checked_add(&self, other: &Vector<T, N>) -> Option<Self> {
+        self.try_zip_with(other, |&x, &y| x.checked_add(y))
+    }
+
+    #[inline]
+    pub fn checked_sub(&self, other: &Vector<T, N>) -> Option<Self> {
+        self.try_zip_with(other, |&x, &y| x.checked_sub(y))
+    }
+
+    #[inline]
+    pub fn checked_mul(&self, rhs: T) -> Option<Self> {
+        self.try_zip_with(&Self::zero(), |&x, _| x.checked_mul(rhs))
+    }
+
+    /// Converts to a vector with a wider scalar type. This never loses information.
+    #[inline]
+    pub fn cast<U>(self) -> Vector<U, N>
+    where
+        U: Scalar + From<T>,
+    {
+        Vector::from_iter(self.coords.into_iter().map(U::from))
+    }
+
+    /// Converts to a vector with a different scalar type,
+    /// or returns `None` if any coordinate doesn't fit.
+    #[inline]
+    pub fn try_cast<U>(self) -> Option<Vector<U, N>>
+    where
+        U: Scalar + TryFrom<T>,
+    {
+        let mut coords = [U::ZERO; N];
+        for (dest, src) in coords.iter_mut().zip(self.coords) {
+            *dest = U::try_from(src).ok()?;
+        }
+        Some(coords.into())
+    }
+
+    fn from_iter(iter: impl Iterator<Item = T>) -> Self {
+        let mut coords = [T::ZERO; N];
         coords
             .iter_mut()
             .zip(iter)
@@ -67,13 +221,13 @@ impl<const N: usize> Vector<N> {
     }
 }
 
-impl<const N: usize> Default for Vector<N> {
+impl<T: Scalar, const N: usize> Default for Vector<T, N> {
     fn default() -> Self {
         Self::zero()
     }
 }
 
-impl<const N: usize> Display for Vector<N> {
+impl<T: Display, const N: usize> Display for Vector<T, N> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "(")?;
         for (i, coord) in self.coords.iter().enumerate() {
@@ -87,7 +241,7 @@ impl<const N: usize> Display for Vector<N> {
     }
 }
 
-impl<const N: usize> Debug for Vector<N> {
+impl<T: Debug, const N: usize> Debug for Vector<T, N> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let mut f = f.debug_tuple(&format!("Vector<{}>", N));
         for coord in self.coords.iter() {
@@ -97,35 +251,52 @@ impl<const N: usize> Debug for Vector<N> {
     }
 }
 
-impl<const N: usize> From<[i32; N]> for Vector<N> {
-    fn from(coords: [i32; N]) -> Self {
+impl<T, const N: usize> From<[T; N]> for Vector<T, N> {
+    fn from(coords: [T; N]) -> Self {
         Self { coords }
     }
 }
 
-impl<const N: usize> From<Vector<N>> for [i32; N] {
-    fn from(vector: Vector<N>) -> Self {
+impl<T, const N: usize> From<Vector<T, N>> for [T; N] {
+    fn from(vector: Vector<T, N>) -> Self {
         vector.coords
     }
 }
 
-impl<const N: usize> Add for Vector<N> {
+macro_rules! impl_widen {
+    ($from:ty => $($to:ty),*) => {
+        $(
+            impl<const N: usize> From<Vector<$from, N>> for Vector<$to, N> {
+                fn from(vector: Vector<$from, N>) -> Self {
+                    vector.cast()
+                }
+            }
+        )*
+    };
+}
+
+impl_widen!(i8 => i16, i32, i64, i128, isize);
+impl_widen!(i16 => i32, i64, i128, isize);
+impl_widen!(i32 => i64, i128);
+impl_widen!(i64 => i128);
+
+impl<T: Scalar, const N: usize> Add for Vector<T, N> {
     type Output = Self;
 
-    fn add(self, other: Vector<N>) -> Self {
-        self.zip_with(&other, |x, y| x + y)
+    fn add(self, other: Vector<T, N>) -> Self {
+        self.zip_with(&other, |&x, &y| x + y)
     }
 }
 
-impl<const N: usize> Sub for Vector<N> {
+impl<T: Scalar, const N: usize> Sub for Vector<T, N> {
     type Output = Self;
 
-    fn sub(self: Vector<N>, other: Vector<N>) -> Self {
-        self.zip_with(&other, |x, y| x - y)
+    fn sub(self: Vector<T, N>, other: Vector<T, N>) -> Self {
+        self.zip_with(&other, |&x, &y| x - y)
     }
 }
 
-impl<const N: usize> Neg for Vector<N> {
+impl<T: Scalar, const N: usize> Neg for Vector<T, N> {
     type Output = Self;
 
     fn neg(self) -> Self {
@@ -133,123 +304,154 @@ impl<const N: usize> Neg for Vector<N> {
     }
 }
 
-impl<const N: usize> AddAssign for Vector<N> {
+impl<T: Scalar, const N: usize> AddAssign for Vector<T, N> {
     fn add_assign(&mut self, other: Self) {
-        self.zip_in_place(&other, |x, y| x.add_assign(y));
+        self.zip_in_place(&other, |x, &y| x.add_assign(y));
     }
 }
 
-impl<const N: usize> SubAssign for Vector<N> {
+impl<T: Scalar, const N: usize> SubAssign for Vector<T, N> {
     fn sub_assign(&mut self, other: Self) {
-        self.zip_in_place(&other, |x, y| x.sub_assign(y));
+        self.zip_in_place(&other, |x, &y| x.sub_assign(y));
     }
 }
 
-impl<const N: usize> Mul<i32> for Vector<N> {
+impl<T: Scalar, const N: usize> Mul<T> for Vector<T, N> {
     type Output = Self;
 
-    fn mul(self, rhs: i32) -> Self::Output {
-        self.map(|x| x * rhs)
+    fn mul(self, rhs: T) -> Self::Output {
+        self.map(|&x| x * rhs)
     }
 }
 
-impl<const N: usize> MulAssign<i32> for Vector<N> {
-    fn mul_assign(&mut self, rhs: i32) {
+impl<T: Scalar, const N: usize> MulAssign<T> for Vector<T, N> {
+    fn mul_assign(&mut self, rhs: T) {
         self.for_each(|x| x.mul_assign(rhs));
     }
 }
 
-impl<const N: usize> Div<i32> for Vector<N> {
+impl<T: Scalar, const N: usize> Div<T> for Vector<T, N> {
     type Output = Self;
 
-    fn div(self, rhs: i32) -> Self::Output {
-        self.map(|x| x / rhs)
+    fn div(self, rhs: T) -> Self::Output {
+        self.map(|&x| x / rhs)
     }
 }
 
-impl<const N: usize> DivAssign<i32> for Vector<N> {
-    fn div_assign(&mut self, rhs: i32) {
+impl<T: Scalar, const N: usize> DivAssign<T> for Vector<T, N> {
+    fn div_assign(&mut self, rhs: T) {
         self.for_each(|x| x.div_assign(rhs));
     }
 }
 
-pub type Vector2D = Vector<2>;
+pub type Vector2D<T = i32> = Vector<T, 2>;
 
-#[allow(dead_code)]
-impl Vector2D {
-    pub const fn new(x: i32, y: i32) -> Self {
+impl<T: Scalar> Vector2D<T> {
+    pub const fn new(x: T, y: T) -> Self {
         Self { coords: [x, y] }
     }
 
     #[inline]
-    pub fn x(&self) -> i32 {
+    pub fn x(&self) -> T {
         self.coords[0]
     }
 
     #[inline]
-    pub fn x_mut(&mut self) -> &mut i32 {
+    pub fn x_mut(&mut self) -> &mut T {
         &mut self.coords[0]
     }
 
     #[inline]
-    pub fn y(&self) -> i32 {
+    pub fn y(&self) -> T {
         self.coords[1]
     }
 
     #[inline]
-    pub fn y_mut(&mut self) -> &mut i32 {
+    pub fn y_mut(&mut self) -> &mut T {
         &mut self.coords[1]
     }
 
+    /// Rotates by 90 degrees to the left (counter-clockwise),
+    /// with the Y axis pointing down as in the puzzle maps.
+    #[inline]
+    pub fn rotate_left(self) -> Self {
+        Vector2D::new(self.y(), -self.x())
+    }
+
+    /// Rotates by 90 degrees to the right (clockwise),
+    /// with the Y axis pointing down as in the puzzle maps.
+    #[inline]
+    pub fn rotate_right(self) -> Self {
+        Vector2D::new(-self.y(), self.x())
+    }
+
     pub fn neighbours(self) -> impl Iterator<Item = Self> {
+        let (zero, one) = (T::ZERO, T::ONE);
         [
-            self + Vector2D::new(0, -1),
-            self + Vector2D::new(-1, 0),
-            self + Vector2D::new(1, 0),
-            self + Vector2D::new(0, 1),
+            self + Vector2D::new(zero, -one),
+            self + Vector2D::new(-one, zero),
+            self + Vector2D::new(one, zero),
+            self + Vector2D::new(zero, one),
         ]
         .into_iter()
     }
 
     pub fn neighbours_diagonal(self) -> impl Iterator<Item = Self> {
+        let (zero, one) = (T::ZERO, T::ONE);
         [
-            self + Vector2D::new(-1, -1),
-            self + Vector2D::new(0, -1),
-            self + Vector2D::new(1, -1),
-            self + Vector2D::new(-1, 0),
-            self + Vector2D::new(1, 0),
-            self + Vector2D::new(-1, 1),
-            self + Vector2D::new(0, 1),
-            self + Vector2D::new(1, 1),
+            self + Vector2D::new(-one, -one),
+            self + Vector2D::new(zero, -one),
+            self + Vector2D::new(one, -one),
+            self + Vector2D::new(-one, zero),
+            self + Vector2D::new(one, zero),
+            self + Vector2D::new(-one, one),
+            self + Vector2D::new(zero, one),
+            self + Vector2D::new(one, one),
         ]
         .into_iter()
     }
 }
 
-pub type Vector3D = Vector<3>;
+pub type Vector3D<T = i32> = Vector<T, 3>;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
 
-#[allow(dead_code)]
-impl Vector3D {
-    pub const fn new(x: i32, y: i32, z: i32) -> Self {
+impl<T: Scalar> Vector3D<T> {
+    pub const fn new(x: T, y: T, z: T) -> Self {
         Self { coords: [x, y, z] }
     }
 
     #[inline]
-    pub fn x(&self) -> i32 {
+    pub fn x(&self) -> T {
         self.coords[0]
     }
 
     #[inline]
-    pub fn y(&self) -> i32 {
+    pub fn y(&self) -> T {
         self.coords[1]
     }
 
     #[inline]
-    pub fn z(&self) -> i32 {
+    pub fn z(&self) -> T {
         self.coords[2]
     }
 
-    pub fn cross_product(self, other: Vector3D) -> Vector3D {
+    /// Rotates by 90 degrees around the given axis, following the right-hand rule.
+    pub fn rotate(self, axis: Axis) -> Self {
+        let [x, y, z] = self.coords;
+        match axis {
+            Axis::X => Vector3D::new(x, -z, y),
+            Axis::Y => Vector3D::new(z, y, -x),
+            Axis::Z => Vector3D::new(-y, x, z),
+        }
+    }
+
+    pub fn cross_product(self, other: Vector3D<T>) -> Vector3D<T> {
         // https://en.wikipedia.org/wiki/Cross_product
         Vector3D::new(
             self.y() * other.z() - self.z() * other.y(),
@@ -259,13 +461,14 @@ impl Vector3D {
     }
 
     pub fn neighbours(self) -> impl Iterator<Item = Self> {
+        let (zero, one) = (T::ZERO, T::ONE);
         [
-            self + Vector3D::new(-1, 0, 0),
-            self + Vector3D::new(1, 0, 0),
-            self + Vector3D::new(0, -1, 0),
-            self + Vector3D::new(0, 1, 0),
-            self + Vector3D::new(0, 0, -1),
-            self + Vector3D::new(0, 0, 1),
+            self + Vector3D::new(-one, zero, zero),
+            self + Vector3D::new(one, zero, zero),
+            self + Vector3D::new(zero, -one, zero),
+            self + Vector3D::new(zero, one, zero),
+            self + Vector3D::new(zero, zero, -one),
+            self + Vector3D::new(zero, zero, one),
         ]
         .into_iter()
     }
@@ -343,6 +546,33 @@ mod tests {
             assert_eq!(vector, Vector2D::from([9, 12]));
         }
 
+        #[test]
+        fn test_distances() {
+            let vector = Vector2D::new(3, -4);
+            assert_eq!(vector.manhattan_distance(), 7);
+            assert_eq!(vector.chebyshev_distance(), 4);
+            assert_eq!(vector.dot(&Vector2D::new(2, 1)), 2);
+            assert_eq!(vector.signum(), Vector2D::new(1, -1));
+        }
+
+        #[test]
+        fn test_min_max() {
+            let a = Vector2D::new(3, -4);
+            let b = Vector2D::new(-1, 2);
+            assert_eq!(a.min(&b), Vector2D::new(-1, -4));
+            assert_eq!(a.max(&b), Vector2D::new(3, 2));
+        }
+
+        #[test]
+        fn test_rotate() {
+            let up = Vector2D::new(0, -1);
+            assert_eq!(up.rotate_right(), Vector2D::new(1, 0));
+            assert_eq!(up.rotate_left(), Vector2D::new(-1, 0));
+            assert_eq!(up.rotate_left().rotate_right(), up);
+            let vector = Vector2D::new(3, 4);
+            assert_eq!(vector.rotate_right().rotate_right(), Vector2D::new(-3, -4));
+        }
+
         #[test]
         fn test_display() {
             assert_eq!(format!("{}", Vector2D::new(3, 4)), "(3, 4)");
@@ -417,6 +647,20 @@ mod tests {
             assert_eq!(format!("{}", Vector3D::new(3, 4, 5)), "(3, 4, 5)");
         }
 
+        #[test]
+        fn test_rotate() {
+            let x = Vector3D::new(1, 0, 0);
+            let y = Vector3D::new(0, 1, 0);
+            let z = Vector3D::new(0, 0, 1);
+            assert_eq!(x.rotate(Axis::Z), y);
+            assert_eq!(y.rotate(Axis::X), z);
+            assert_eq!(z.rotate(Axis::Y), x);
+            assert_eq!(x.rotate(Axis::X), x);
+            let vector = Vector3D::new(3, 4, 5);
+            let rotated = (0..4).fold(vector, |v, _| v.rotate(Axis::Y));
+            assert_eq!(rotated, vector);
+        }
+
         #[test]
         fn test_debug() {
             assert_eq!(
@@ -425,4 +669,46 @@ mod tests {
             );
         }
     }
+
+    mod scalar {
+        use super::*;
+
+        #[test]
+        fn test_i64() {
+            let vector = Vector2D::<i64>::new(3_000_000_000, -4);
+            assert_eq!(vector * 2, Vector2D::from([6_000_000_000, -8]));
+            assert_eq!(vector.manhattan_distance(), 3_000_000_004);
+        }
+
+        #[test]
+        fn test_widen() {
+            let vector = Vector3D::new(3, -4, 5);
+            assert_eq!(
+                Vector3D::<i64>::from(vector),
+                Vector3D::<i64>::new(3, -4, 5)
+            );
+            assert_eq!(vector.cast::<i128>(), Vector3D::<i128>::new(3, -4, 5));
+        }
+
+        #[test]
+        fn test_try_cast() {
+            let vector = Vector2D::<i64>::new(3, 1 << 40);
+            assert_eq!(vector.try_cast::<i32>(), None);
+            assert_eq!(
+                Vector2D::<i64>::new(3, 4).try_cast::<i32>(),
+                Some(Vector2D::new(3, 4))
+            );
+        }
+
+        #[test]
+        fn test_checked() {
+            let vector = Vector2D::new(i32::MAX, 0);
+            assert_eq!(vector.checked_add(&Vector2D::new(1, 0)), None);
+            assert_eq!(
+                vector.checked_sub(&Vector2D::new(1, 0)),
+                Some(Vector2D::new(i32::MAX - 1, 0))
+            );
+            assert_eq!(vector.checked_mul(2), None);
+        }
+    }
 }
@@ -0,0 +1,331 @@
+use std::fmt::{Debug, Display};
+use std::str::FromStr;
+
+use crate::util::{lines, ParseError};
+
+/// The instructions understood by a `Vm`.
+///
+/// Instructions are parsed from (and displayed as) one line of assembly each.
+pub trait InstructionSet: Copy + Debug + Display + FromStr<Err = ParseError> {
+    /// The names of the registers, in order.
+    const REGISTERS: &'static [&'static str];
+
+    /// The values of the registers when the program starts.
+    fn initial_registers() -> Vec<i32> {
+        vec![0; Self::REGISTERS.len()]
+    }
+
+    /// The number of cycles this instruction takes to complete.
+    ///
+    /// Must be at least 1: every instruction takes up at least one cycle.
+    fn cycles(&self) -> usize;
+
+    /// Applies this instruction to the registers, at the end of its last cycle.
+    fn execute(&self, registers: &mut [i32]);
+}
+
+/// Parses a program, one instruction per line.
+pub fn assemble<I: InstructionSet>(day: u32, source: &str) -> Result<Vec<I>, ParseError> {
+    lines(day, source).map(|line| line.parse_line()).collect()
+}
+
+/// Formats a program, one instruction per line.
+#[allow(dead_code)]
+pub fn disassemble<I: InstructionSet>(program: &[I]) -> String {
+    program
+        .iter()
+        .map(|instruction| format!("{}\n", instruction))
+        .collect()
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Breakpoint {
+    /// Stop right before the given (1-based) cycle starts.
+    #[allow(dead_code)]
+    Cycle(usize),
+    /// Stop as soon as the register with the given index changes to the given value.
+    #[allow(dead_code)]
+    Register(usize, i32),
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum StopReason {
+    /// The program has finished.
+    Halted,
+    /// The breakpoint with the given index was hit.
+    Breakpoint(usize),
+}
+
+/// The state of the machine during a single cycle.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Snapshot<I> {
+    pub cycle: usize,
+    pub pc: usize,
+    /// The instruction being executed, or `None` once the program has finished.
+    pub instruction: Option<I>,
+    pub registers: Vec<i32>,
+}
+
+/// A virtual machine that executes a program one cycle at a time.
+///
+/// Instructions take effect at the *end* of their last cycle,
+/// so `registers()` always returns the values *during* the next cycle.
+#[derive(Debug, Clone)]
+pub struct Vm<I: InstructionSet> {
+    program: Vec<I>,
+    pc: usize,
+    /// The next cycle to run, starting from 1.
+    cycle: usize,
+    /// The number of cycles left for the instruction at `pc`, if it has started.
+    remaining_cycles: Option<usize>,
+    registers: Vec<i32>,
+    #[allow(dead_code)]
+    breakpoints: Vec<Breakpoint>,
+    trace: Option<Vec<Snapshot<I>>>,
+}
+
+impl<I: InstructionSet> Vm<I> {
+    pub fn new(program: Vec<I>) -> Self {
+        Self {
+            program,
+            pc: 0,
+            cycle: 1,
+            remaining_cycles: None,
+            registers: I::initial_registers(),
+            breakpoints: Vec::new(),
+            trace: None,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn program(&self) -> &[I] {
+        &self.program
+    }
+
+    #[allow(dead_code)]
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// The next cycle to run, starting from 1.
+    #[allow(dead_code)]
+    pub fn cycle(&self) -> usize {
+        self.cycle
+    }
+
+    pub fn registers(&self) -> &[i32] {
+        &self.registers
+    }
+
+    /// Finds the index of a register by its name.
+    #[allow(dead_code)]
+    pub fn register_index(name: &str) -> Option<usize> {
+        I::REGISTERS.iter().position(|&register| register == name)
+    }
+
+    #[allow(dead_code)]
+    pub fn is_halted(&self) -> bool {
+        self.pc >= self.program.len()
+    }
+
+    /// Adds a breakpoint for `run()`, and returns its index.
+    #[allow(dead_code)]
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        self.breakpoints.push(breakpoint);
+        self.breakpoints.len() - 1
+    }
+
+    /// Starts recording a snapshot of every cycle.
+    #[allow(dead_code)]
+    pub fn enable_trace(&mut self) {
+        self.trace.get_or_insert_with(Vec::new);
+    }
+
+    /// The snapshots recorded since tracing was enabled.
+    #[allow(dead_code)]
+    pub fn trace(&self) -> &[Snapshot<I>] {
+        self.trace.as_deref().unwrap_or_default()
+    }
+
+    /// Runs a single cycle.
+    /// Once the program has finished, the machine idles and the registers no longer change.
+    ///
+    /// Panics if an instruction takes no cycles, see `InstructionSet::cycles()`.
+    pub fn step(&mut self) {
+        let instruction = self.program.get(self.pc).copied();
+        if let Some(trace) = &mut self.trace {
+            trace.push(Snapshot {
+                cycle: self.cycle,
+                pc: self.pc,
+                instruction,
+                registers: self.registers.clone(),
+            });
+        }
+        self.cycle += 1;
+        let Some(instruction) = instruction else {
+            return;
+        };
+        let remaining_cycles = self.remaining_cycles.get_or_insert_with(|| {
+            let cycles = instruction.cycles();
+            assert!(cycles >= 1, "{} takes no cycles", instruction);
+            cycles
+        });
+        *remaining_cycles -= 1;
+        if *remaining_cycles == 0 {
+            instruction.execute(&mut self.registers);
+            self.remaining_cycles = None;
+            self.pc += 1;
+        }
+    }
+
+    /// Runs until the program finishes or a breakpoint is hit.
+    #[allow(dead_code)]
+    pub fn run(&mut self) -> StopReason {
+        while !self.is_halted() {
+            let old_registers = self.registers.clone();
+            self.step();
+            let hit = self
+                .breakpoints
+                .iter()
+                .position(|&breakpoint| match breakpoint {
+                    Breakpoint::Cycle(cycle) => self.cycle == cycle,
+                    Breakpoint::Register(register, value) => {
+                        self.registers[register] == value && old_registers[register] != value
+                    }
+                });
+            if let Some(index) = hit {
+                return StopReason::Breakpoint(index);
+            }
+        }
+        StopReason::Halted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Line;
+    use std::fmt::Formatter;
+
+    /// A small instruction set with two registers.
+    #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+    enum Toy {
+        Inc(usize),
+        Swap,
+        /// Invalid, because it takes no cycles.
+        Nop,
+    }
+
+    impl InstructionSet for Toy {
+        const REGISTERS: &'static [&'static str] = &["a", "b"];
+
+        fn cycles(&self) -> usize {
+            match self {
+                Toy::Inc(_) => 1,
+                Toy::Swap => 3,
+                Toy::Nop => 0,
+            }
+        }
+
+        fn execute(&self, registers: &mut [i32]) {
+            match *self {
+                Toy::Inc(register) => registers[register] += 1,
+                Toy::Swap => registers.swap(0, 1),
+                Toy::Nop => {}
+            }
+        }
+    }
+
+    impl FromStr for Toy {
+        type Err = ParseError;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let line = Line::new(0, 1, s);
+            match s {
+                "inc a" => Ok(Toy::Inc(0)),
+                "inc b" => Ok(Toy::Inc(1)),
+                "swap" => Ok(Toy::Swap),
+                "nop" => Ok(Toy::Nop),
+                _ => Err(line.error(s, "invalid instruction")),
+            }
+        }
+    }
+
+    impl Display for Toy {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Toy::Inc(register) => write!(f, "inc {}", Toy::REGISTERS[*register]),
+                Toy::Swap => write!(f, "swap"),
+                Toy::Nop => write!(f, "nop"),
+            }
+        }
+    }
+
+    const PROGRAM: &str = "inc a\ninc a\nswap\ninc a\n";
+
+    #[test]
+    fn test_assemble() {
+        let program = assemble::<Toy>(0, PROGRAM).unwrap();
+        assert_eq!(
+            program,
+            vec![Toy::Inc(0), Toy::Inc(0), Toy::Swap, Toy::Inc(0)]
+        );
+        assert_eq!(disassemble(&program), PROGRAM);
+        let err = assemble::<Toy>(0, "inc a\ninc c").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 1));
+    }
+
+    #[test]
+    fn test_run() {
+        let mut vm = Vm::new(assemble::<Toy>(0, PROGRAM).unwrap());
+        assert_eq!(vm.run(), StopReason::Halted);
+        assert_eq!(vm.registers(), &[1, 2]);
+        // 1 + 1 + 3 + 1 cycles
+        assert_eq!(vm.cycle(), 7);
+        vm.step();
+        assert_eq!(vm.registers(), &[1, 2]);
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let mut vm = Vm::new(assemble::<Toy>(0, PROGRAM).unwrap());
+        let b = Vm::<Toy>::register_index("b").unwrap();
+        let on_b = vm.add_breakpoint(Breakpoint::Register(b, 2));
+        let on_cycle = vm.add_breakpoint(Breakpoint::Cycle(4));
+        assert_eq!(vm.run(), StopReason::Breakpoint(on_cycle));
+        assert_eq!((vm.pc(), vm.registers()), (2, &[2, 0][..]));
+        assert_eq!(vm.run(), StopReason::Breakpoint(on_b));
+        assert_eq!((vm.cycle(), vm.registers()), (6, &[0, 2][..]));
+        assert_eq!(vm.run(), StopReason::Halted);
+    }
+
+    #[test]
+    fn test_trace() {
+        let mut vm = Vm::new(assemble::<Toy>(0, "inc b\nswap").unwrap());
+        vm.enable_trace();
+        vm.run();
+        let registers = vm
+            .trace()
+            .iter()
+            .map(|snapshot| (snapshot.cycle, snapshot.registers.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            registers,
+            vec![
+                (1, vec![0, 0]),
+                (2, vec![0, 1]),
+                (3, vec![0, 1]),
+                (4, vec![0, 1])
+            ]
+        );
+        assert_eq!(vm.trace()[1].instruction, Some(Toy::Swap));
+        assert_eq!(vm.registers(), &[1, 0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "nop takes no cycles")]
+    fn test_instruction_without_cycles() {
+        Vm::new(assemble::<Toy>(0, "inc a\nnop").unwrap()).run();
+    }
+}